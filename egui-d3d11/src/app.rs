@@ -1,54 +1,94 @@
 use crate::{
     backup::BackupState,
-    input::{InputCollector, InputResult},
-    mesh::{create_index_buffer, create_vertex_buffer, GpuMesh, GpuVertex},
-    shader::CompiledShaders,
-    texture::TextureAllocator,
+    callback::{CallbackInfo, D3D11CallbackFn},
+    capture::{CaptureMode, WndProcResult},
+    clip::clip_rect_to_scissor,
+    clipboard::{default_clipboard, ClipboardBackend},
+    color::ColorMode,
+    cursor::{is_client_area, set_cursor, WM_SETCURSOR},
+    decode::is_release,
+    device::DeviceObjects,
+    error::{Error, Result},
+    input::{Hotkey, InputCollector, InputResult},
+    mapping::CoordinateMapping,
+    mesh::{DrawCommand, GpuMesh},
+    mip::MipMode,
+    sampler::SamplerKey,
+    texture::{NativeTextures, TextureAllocator, TextureMemoryPolicy, TextureMemoryStats},
+    url::{is_web_url, open_url, ShellUrlOpener, UrlOpener},
+};
+use egui::{
+    epaint::{ClippedPrimitive, Primitive},
+    output::OpenUrl,
+    Context, CursorIcon, Pos2, Rect, TextureFilter, TextureId, Vec2,
 };
-use clipboard::{windows_clipboard::WindowsClipboardContext, ClipboardProvider};
-use egui::{epaint::Primitive, Context};
 use once_cell::sync::OnceCell;
 use parking_lot::{const_mutex, Mutex, MutexGuard};
-use std::{mem::size_of, ops::DerefMut};
+use std::{
+    ops::DerefMut,
+    sync::atomic::{AtomicBool, Ordering},
+};
 use windows::{
     core::HRESULT,
     Win32::{
-        Foundation::{HWND, LPARAM, RECT, WPARAM},
+        Foundation::{HWND, LPARAM, WPARAM},
         Graphics::{
-            Direct3D::D3D11_PRIMITIVE_TOPOLOGY_TRIANGLELIST,
             Direct3D11::{
-                ID3D11Device, ID3D11DeviceContext, ID3D11InputLayout, ID3D11RenderTargetView,
-                ID3D11Texture2D, D3D11_APPEND_ALIGNED_ELEMENT, D3D11_BLEND_DESC,
-                D3D11_BLEND_INV_SRC_ALPHA, D3D11_BLEND_ONE, D3D11_BLEND_OP_ADD,
-                D3D11_BLEND_SRC_ALPHA, D3D11_COLOR_WRITE_ENABLE_ALL, D3D11_COMPARISON_ALWAYS,
-                D3D11_CULL_NONE, D3D11_FILL_SOLID, D3D11_FILTER_MIN_MAG_MIP_LINEAR,
-                D3D11_INPUT_ELEMENT_DESC, D3D11_INPUT_PER_VERTEX_DATA, D3D11_RASTERIZER_DESC,
-                D3D11_RENDER_TARGET_BLEND_DESC, D3D11_SAMPLER_DESC, D3D11_TEXTURE_ADDRESS_BORDER,
-                D3D11_VIEWPORT,
-            },
-            Dxgi::{
-                Common::{
-                    DXGI_FORMAT_R32G32B32A32_FLOAT, DXGI_FORMAT_R32G32_FLOAT, DXGI_FORMAT_R32_UINT,
-                },
-                IDXGISwapChain,
+                ID3D11Device, ID3D11DeviceContext, ID3D11ShaderResourceView, D3D11_VIEWPORT,
             },
+            Dxgi::IDXGISwapChain,
         },
-        UI::WindowsAndMessaging::GetClientRect,
     },
 };
 
 struct AppData<T> {
-    render_view: Option<ID3D11RenderTargetView>,
     ui: Box<dyn FnMut(&Context, &mut T) + 'static>,
+    background: Option<Box<dyn FnMut(&mut T) + 'static>>,
     tex_alloc: TextureAllocator,
-    input_layout: ID3D11InputLayout,
+    objects: Option<DeviceObjects>,
     input_collector: InputCollector,
-    shaders: CompiledShaders,
     backup: BackupState,
+    color_mode: ColorMode,
+    capture_mode: CaptureMode,
+    cursor_icon: CursorIcon,
+    backbuffer_size: (f32, f32),
+    url_opener: Box<dyn UrlOpener>,
+    url_filter: Box<dyn FnMut(&OpenUrl) -> bool>,
     ctx: Context,
     state: T,
 }
 
+impl<T> AppData<T> {
+    fn process_message(&mut self, umsg: u32, wparam: WPARAM, lparam: LPARAM) -> InputResult {
+        if umsg == WM_SETCURSOR && self.input_collector.is_visible() && is_client_area(lparam.0) {
+            // Game is left in charge of the cursor unless egui has something to say.
+            if self.cursor_icon != CursorIcon::Default || self.ctx.is_pointer_over_area() {
+                set_cursor(self.cursor_icon);
+                return InputResult::Cursor;
+            }
+        }
+
+        self.input_collector.process(umsg, wparam.0, lparam.0)
+    }
+}
+
+/// Changes requested through methods which are safe to call from inside of the ui.
+/// Guarded by its own lock, which is never held while the ui runs.
+/// Applied as soon as the app's data is locked again.
+struct PendingChanges<T> {
+    visible: Option<bool>,
+    mip_modes: Vec<(TextureId, MipMode)>,
+    memory_policy: Option<TextureMemoryPolicy>,
+    pixels_per_point: Option<Option<f32>>,
+    capture_mode: Option<CaptureMode>,
+    toggle_key: Option<Option<Hotkey>>,
+    background: Option<Box<dyn FnMut(&mut T) + 'static>>,
+    url_opener: Option<Box<dyn UrlOpener>>,
+    url_filter: Option<Box<dyn FnMut(&OpenUrl) -> bool>>,
+    coordinate_mapping: Option<CoordinateMapping>,
+    resync_modifiers: Option<bool>,
+}
+
 /// Heart and soul of this integration.
 /// Main methods you are going to use are:
 /// * [`Self::present`] - Should be called inside of hook or before present.
@@ -56,39 +96,14 @@ struct AppData<T> {
 /// * [`Self::wnd_proc`] - Should be called on each `WndProc`.
 pub struct DirectX11App<T = ()> {
     data: Mutex<Option<AppData<T>>>,
+    pending: Mutex<PendingChanges<T>>,
+    native: Mutex<NativeTextures>,
+    /// Copy of the overlay's visibility, readable while the ui runs.
+    visible: AtomicBool,
+    /// Texture memory as of the last present, readable while the ui runs.
+    texture_stats: Mutex<Option<TextureMemoryStats>>,
     hwnd: OnceCell<HWND>,
-}
-
-impl<T> DirectX11App<T> {
-    const INPUT_ELEMENTS_DESC: [D3D11_INPUT_ELEMENT_DESC; 3] = [
-        D3D11_INPUT_ELEMENT_DESC {
-            SemanticName: pc_str!("POSITION"),
-            SemanticIndex: 0,
-            Format: DXGI_FORMAT_R32G32_FLOAT,
-            InputSlot: 0,
-            AlignedByteOffset: 0,
-            InputSlotClass: D3D11_INPUT_PER_VERTEX_DATA,
-            InstanceDataStepRate: 0,
-        },
-        D3D11_INPUT_ELEMENT_DESC {
-            SemanticName: pc_str!("TEXCOORD"),
-            SemanticIndex: 0,
-            Format: DXGI_FORMAT_R32G32_FLOAT,
-            InputSlot: 0,
-            AlignedByteOffset: D3D11_APPEND_ALIGNED_ELEMENT,
-            InputSlotClass: D3D11_INPUT_PER_VERTEX_DATA,
-            InstanceDataStepRate: 0,
-        },
-        D3D11_INPUT_ELEMENT_DESC {
-            SemanticName: pc_str!("COLOR"),
-            SemanticIndex: 0,
-            Format: DXGI_FORMAT_R32G32B32A32_FLOAT,
-            InputSlot: 0,
-            AlignedByteOffset: D3D11_APPEND_ALIGNED_ELEMENT,
-            InputSlotClass: D3D11_INPUT_PER_VERTEX_DATA,
-            InstanceDataStepRate: 0,
-        },
-    ];
+    color_mode: ColorMode,
 }
 
 impl<T> DirectX11App<T> {
@@ -96,10 +111,33 @@ impl<T> DirectX11App<T> {
     pub const fn new() -> Self {
         Self {
             data: const_mutex(None),
+            pending: const_mutex(PendingChanges {
+                visible: None,
+                mip_modes: Vec::new(),
+                memory_policy: None,
+                pixels_per_point: None,
+                capture_mode: None,
+                toggle_key: None,
+                background: None,
+                url_opener: None,
+                url_filter: None,
+                coordinate_mapping: None,
+                resync_modifiers: None,
+            }),
+            native: const_mutex(NativeTextures::new()),
+            visible: AtomicBool::new(true),
+            texture_stats: const_mutex(None),
             hwnd: OnceCell::new(),
+            color_mode: ColorMode::Detect,
         }
     }
 
+    /// Sets how colors are written into the backbuffer. Defaults to [`ColorMode::Detect`].
+    /// Mode is resolved during `init_*`, and again whenever the swapchain or its buffers change.
+    pub const fn with_color_mode(self, color_mode: ColorMode) -> Self {
+        Self { color_mode, ..self }
+    }
+
     /// Checks if the app is ready to draw and if it's safe to invoke `present`, `wndproc`, etc.
     /// `true` means that you have already called an `init_*` on the application.
     pub fn is_ready(&self) -> bool {
@@ -107,6 +145,7 @@ impl<T> DirectX11App<T> {
     }
 
     /// Initializes application and state. You should call this only once!
+    #[inline]
     pub fn init_with_state_context(
         &self,
         swap: &IDXGISwapChain,
@@ -114,43 +153,95 @@ impl<T> DirectX11App<T> {
         state: T,
         context: Context,
     ) {
+        expect!(
+            self.try_init_with_state_context(swap, ui, state, context),
+            "Failed to initialize"
+        )
+    }
+
+    /// Same as [`Self::init_with_state_context`], but returns an error instead of panicking.
+    #[inline]
+    pub fn try_init_with_state_context(
+        &self,
+        swap: &IDXGISwapChain,
+        ui: impl FnMut(&Context, &mut T) + 'static,
+        state: T,
+        context: Context,
+    ) -> Result<()> {
+        self.try_init_with_clipboard(swap, ui, state, context, default_clipboard())
+    }
+
+    /// Initializes application and state with a custom clipboard, e.g. [`crate::MemoryClipboard`]
+    /// to keep the game's clipboard untouched. You should call this only once!
+    #[inline]
+    pub fn init_with_clipboard(
+        &self,
+        swap: &IDXGISwapChain,
+        ui: impl FnMut(&Context, &mut T) + 'static,
+        state: T,
+        context: Context,
+        clipboard: Box<dyn ClipboardBackend>,
+    ) {
+        expect!(
+            self.try_init_with_clipboard(swap, ui, state, context, clipboard),
+            "Failed to initialize"
+        )
+    }
+
+    /// Same as [`Self::init_with_clipboard`], but returns an error instead of panicking.
+    pub fn try_init_with_clipboard(
+        &self,
+        swap: &IDXGISwapChain,
+        ui: impl FnMut(&Context, &mut T) + 'static,
+        state: T,
+        context: Context,
+        clipboard: Box<dyn ClipboardBackend>,
+    ) -> Result<()> {
         unsafe {
             if self.hwnd.get().is_some() {
-                panic_msg!("You must call init only once");
+                return Err(Error::AlreadyInitialized);
             }
 
-            let hwnd = expect!(swap.GetDesc(), "Failed to get swapchain's descriptor").OutputWindow;
+            let desc = swap
+                .GetDesc()
+                .map_err(|e| Error::Device("Failed to get swapchain's descriptor", e))?;
+            let hwnd = desc.OutputWindow;
             if hwnd.0 == -1 {
-                panic_msg!("Invalid output window descriptor");
+                return Err(Error::InvalidWindow);
             }
-            let _ = self.hwnd.set(hwnd);
 
-            let dev: ID3D11Device = expect!(swap.GetDevice(), "Failed to get swapchain's device");
+            let dev: ID3D11Device = swap
+                .GetDevice()
+                .map_err(|e| Error::Device("Failed to get swapchain's device", e))?;
 
-            let backbuffer: ID3D11Texture2D =
-                expect!(swap.GetBuffer(0), "Failed to get swapchain's backbuffer");
-            let render_view = Some(expect!(
-                dev.CreateRenderTargetView(backbuffer, 0 as _),
-                "Failed to create new render target view"
-            ));
-
-            let shaders = CompiledShaders::new(&dev);
-            let input_layout = expect!(
-                dev.CreateInputLayout(&Self::INPUT_ELEMENTS_DESC, shaders.bytecode()),
-                "Failed to create input layout"
-            );
+            let mut objects = DeviceObjects::new(dev)?;
+            let backbuffer_size = objects.create_render_view(swap)?;
+            let color_mode = self.color_mode.resolve(desc.BufferDesc.Format);
 
             *self.data.lock() = Some(AppData {
-                input_collector: InputCollector::new(hwnd),
-                tex_alloc: TextureAllocator::default(),
+                input_collector: InputCollector::new(
+                    hwnd,
+                    clipboard,
+                    Vec2::new(backbuffer_size.0, backbuffer_size.1),
+                ),
+                tex_alloc: TextureAllocator::new(color_mode),
                 backup: BackupState::default(),
                 ui: Box::new(ui),
+                background: None,
                 ctx: context,
-                input_layout,
-                render_view,
-                shaders,
+                objects: Some(objects),
+                color_mode,
+                capture_mode: CaptureMode::default(),
+                cursor_icon: CursorIcon::Default,
+                backbuffer_size,
+                url_opener: Box::new(ShellUrlOpener::new()),
+                url_filter: Box::new(|url| is_web_url(&url.url)),
                 state,
             });
+
+            // Only now the app is ready, failed init can be retried.
+            let _ = self.hwnd.set(hwnd);
+            Ok(())
         }
     }
 
@@ -165,120 +256,176 @@ impl<T> DirectX11App<T> {
         self.init_with_state_context(swap, ui, state, Context::default())
     }
 
+    /// Same as [`Self::init_with_state`], but returns an error instead of panicking.
+    #[inline]
+    pub fn try_init_with_state(
+        &self,
+        swap: &IDXGISwapChain,
+        ui: impl FnMut(&Context, &mut T) + 'static,
+        state: T,
+    ) -> Result<()> {
+        self.try_init_with_state_context(swap, ui, state, Context::default())
+    }
+
     /// Initializes application and state while allowing you to mutate the initial state of the egui's context. You should call this only once!
     #[inline]
     pub fn init_with_mutate(
         &self,
         swap: &IDXGISwapChain,
         ui: impl FnMut(&Context, &mut T) + 'static,
-        mut state: T,
+        state: T,
         mutate: impl FnOnce(&mut Context, &mut T),
     ) {
+        expect!(
+            self.try_init_with_mutate(swap, ui, state, mutate),
+            "Failed to initialize"
+        )
+    }
+
+    /// Same as [`Self::init_with_mutate`], but returns an error instead of panicking.
+    #[inline]
+    pub fn try_init_with_mutate(
+        &self,
+        swap: &IDXGISwapChain,
+        ui: impl FnMut(&Context, &mut T) + 'static,
+        mut state: T,
+        mutate: impl FnOnce(&mut Context, &mut T),
+    ) -> Result<()> {
         let mut ctx = Context::default();
         mutate(&mut ctx, &mut state);
 
-        self.init_with_state_context(swap, ui, state, ctx);
+        self.try_init_with_state_context(swap, ui, state, ctx)
     }
 
     fn lock_data(&self) -> impl DerefMut<Target = AppData<T>> + '_ {
-        MutexGuard::map(self.data.lock(), |app| {
-            expect!(app.as_mut(), "You need to call init first")
-        })
+        expect!(self.try_lock_data(), "You need to call init first")
     }
-}
 
-impl<T: Default> DirectX11App<T> {
-    /// Initializes application and sets the state to its default value. You should call this only once!
-    #[inline]
-    pub fn init_default(&self, swap: &IDXGISwapChain, ui: impl FnMut(&Context, &mut T) + 'static) {
-        self.init_with_state_context(swap, ui, T::default(), Context::default());
+    fn try_lock_data(&self) -> Result<impl DerefMut<Target = AppData<T>> + '_> {
+        let mut this = MutexGuard::try_map(self.data.lock(), |app| app.as_mut())
+            .map_err(|_| Error::NotInitialized)?;
+        self.apply_pending(&mut this);
+        Ok(this)
     }
-}
 
-impl<T> DirectX11App<T> {
-    /// Present call. Should be called once per original present call, before or inside of hook.
-    #[allow(clippy::cast_ref_to_mut)]
-    pub fn present(&self, swap_chain: &IDXGISwapChain) {
-        unsafe {
-            let this = &mut *self.lock_data();
+    fn apply_pending(&self, this: &mut AppData<T>) {
+        let mut pending = self.pending.lock();
 
-            let (dev, ctx) = &get_device_and_context(swap_chain);
+        if let Some(visible) = pending.visible.take() {
+            this.input_collector.set_visible(visible);
+            self.visible.store(visible, Ordering::Relaxed);
+        }
 
-            this.backup.save(ctx);
+        for (tid, mode) in pending.mip_modes.drain(..) {
+            this.tex_alloc.set_mip_mode(tid, mode);
+        }
 
-            let screen = self.get_screen_size();
+        if let Some(policy) = pending.memory_policy.take() {
+            this.tex_alloc.set_memory_policy(policy);
+        }
 
-            if cfg!(feature = "clear") {
-                ctx.ClearRenderTargetView(&this.render_view, [0.39, 0.58, 0.92, 1.].as_ptr());
-            }
+        if let Some(pixels_per_point) = pending.pixels_per_point.take() {
+            this.input_collector.set_pixels_per_point(pixels_per_point);
+        }
 
-            let output = this.ctx.run(this.input_collector.collect_input(), |ctx| {
-                // Dont look here, it should be fine until someone tries to do something horrible.
-                (this.ui)(ctx, &mut this.state);
-            });
+        if let Some(mode) = pending.capture_mode.take() {
+            this.capture_mode = mode;
+        }
 
-            if !output.textures_delta.is_empty() {
-                this.tex_alloc
-                    .process_deltas(dev, ctx, output.textures_delta);
-            }
+        if let Some(toggle) = pending.toggle_key.take() {
+            this.input_collector.set_toggle_key(toggle);
+        }
 
-            if !output.platform_output.copied_text.is_empty() {
-                let _ = WindowsClipboardContext.set_contents(output.platform_output.copied_text);
-            }
+        if let Some(background) = pending.background.take() {
+            this.background = Some(background);
+        }
 
-            if output.shapes.is_empty() {
-                this.backup.restore(ctx);
-                return;
-            }
+        if let Some(opener) = pending.url_opener.take() {
+            this.url_opener = opener;
+        }
 
-            let primitives = this
-                .ctx
-                .tessellate(output.shapes)
-                .into_iter()
-                .filter_map(|prim| {
-                    if let Primitive::Mesh(mesh) = prim.primitive {
-                        GpuMesh::from_mesh(screen, mesh, prim.clip_rect)
-                    } else {
-                        panic!("Paint callbacks are not yet supported")
-                    }
-                })
-                .collect::<Vec<_>>();
+        if let Some(filter) = pending.url_filter.take() {
+            this.url_filter = filter;
+        }
 
-            // self.set_blend_state(dev, ctx); crashes for some reason
-            self.set_raster_options(dev, ctx);
-            self.set_sampler_state(dev, ctx);
+        if let Some(mapping) = pending.coordinate_mapping.take() {
+            this.input_collector.set_coordinate_mapping(mapping);
+        }
 
-            ctx.RSSetViewports(&[self.get_viewport()]);
-            ctx.OMSetRenderTargets(&[this.render_view.clone()], None);
-            ctx.IASetPrimitiveTopology(D3D11_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
-            ctx.IASetInputLayout(&this.input_layout);
+        if let Some(resync) = pending.resync_modifiers.take() {
+            this.input_collector.set_resync_modifiers(resync);
+        }
+    }
+}
 
-            for mesh in primitives {
-                let idx = create_index_buffer(dev, &mesh);
-                let vtx = create_vertex_buffer(dev, &mesh);
+impl<T: Default> DirectX11App<T> {
+    /// Initializes application and sets the state to its default value. You should call this only once!
+    #[inline]
+    pub fn init_default(&self, swap: &IDXGISwapChain, ui: impl FnMut(&Context, &mut T) + 'static) {
+        self.init_with_state_context(swap, ui, T::default(), Context::default());
+    }
 
-                let texture = this.tex_alloc.get_by_id(mesh.texture_id);
+    /// Same as [`Self::init_default`], but returns an error instead of panicking.
+    #[inline]
+    pub fn try_init_default(
+        &self,
+        swap: &IDXGISwapChain,
+        ui: impl FnMut(&Context, &mut T) + 'static,
+    ) -> Result<()> {
+        self.try_init_with_state_context(swap, ui, T::default(), Context::default())
+    }
+}
 
-                ctx.RSSetScissorRects(&[RECT {
-                    left: mesh.clip.left() as _,
-                    top: mesh.clip.top() as _,
-                    right: mesh.clip.right() as _,
-                    bottom: mesh.clip.bottom() as _,
-                }]);
+impl<T> DirectX11App<T> {
+    /// Present call. Should be called once per original present call, before or inside of hook.
+    /// Updates of textures egui has never allocated are ignored, see [`Error::UnknownTexture`],
+    /// same for empty textures, see [`Error::EmptyTexture`].
+    #[inline]
+    pub fn present(&self, swap_chain: &IDXGISwapChain) {
+        match self.try_present(swap_chain) {
+            // Frame was drawn anyway, only the stale update is lost.
+            Err(Error::UnknownTexture(_) | Error::EmptyTexture(_)) => {}
+            result => expect!(result, "Failed to present"),
+        }
+    }
+
+    /// Same as [`Self::present`], but returns an error instead of panicking.
+    /// Pipeline state of the game is restored even if drawing fails.
+    /// [`Error::UnknownTexture`] and [`Error::EmptyTexture`] are returned after the frame is drawn.
+    pub fn try_present(&self, swap_chain: &IDXGISwapChain) -> Result<()> {
+        unsafe {
+            let this = &mut *self.try_lock_data()?;
 
-                if texture.is_some() {
-                    ctx.PSSetShaderResources(0, &[texture]);
+            if !this.input_collector.is_visible() {
+                if let Some(background) = &mut this.background {
+                    background(&mut this.state);
                 }
+                return Ok(());
+            }
 
-                ctx.IASetVertexBuffers(0, 1, &Some(vtx), &(size_of::<GpuVertex>() as _), &0);
-                ctx.IASetIndexBuffer(idx, DXGI_FORMAT_R32_UINT, 0);
-                ctx.VSSetShader(&this.shaders.vertex, &[]);
-                ctx.PSSetShader(&this.shaders.pixel, &[]);
+            let (dev, ctx) = &get_device_and_context(swap_chain)?;
 
-                ctx.DrawIndexed(mesh.indices.len() as _, 0, 0);
+            match &this.objects {
+                Some(objects) if objects.device == *dev => {
+                    if !objects.targets(swap_chain) {
+                        Self::retarget(this, swap_chain, self.color_mode)?;
+                    }
+                }
+                Some(_) => {
+                    // Views of the old device are useless now, the game has to give new ones.
+                    self.native.lock().mark_stale();
+                    Self::rebuild(this, swap_chain, dev, self.color_mode)?;
+                }
+                // Released by `reset`, natives are already marked.
+                None => Self::rebuild(this, swap_chain, dev, self.color_mode)?,
             }
 
+            this.backup.save(ctx);
+            let result = self.render(this, dev, ctx);
             this.backup.restore(ctx);
+            *self.texture_stats.lock() = Some(this.tex_alloc.memory_stats());
+
+            result
         }
     }
 
@@ -286,32 +433,48 @@ impl<T> DirectX11App<T> {
     /// Do not call the original function before it, instead call it inside of the `original` closure.
     /// # Behavior
     /// In `origin` closure make sure to call the original `ResizeBuffers`.
+    #[inline]
     pub fn resize_buffers(
         &self,
         swap_chain: &IDXGISwapChain,
         original: impl FnOnce() -> HRESULT,
     ) -> HRESULT {
+        expect!(
+            self.try_resize_buffers(swap_chain, original),
+            "Failed to resize buffers"
+        )
+    }
+
+    /// Same as [`Self::resize_buffers`], but returns an error instead of panicking.
+    /// `original` is always called, its result is returned unless recreating the view failed.
+    /// If `original` fails, the view is recreated by the next present instead.
+    pub fn try_resize_buffers(
+        &self,
+        swap_chain: &IDXGISwapChain,
+        original: impl FnOnce() -> HRESULT,
+    ) -> Result<HRESULT> {
         unsafe {
-            let this = &mut *self.lock_data();
-            drop(this.render_view.take());
+            let this = &mut *self.try_lock_data()?;
+            let objects = match &mut this.objects {
+                Some(objects) => objects,
+                // Everything is rebuilt by the next present anyway.
+                None => return Ok(original()),
+            };
+            drop(objects.render_view.take());
 
             let result = original();
+            // Nothing to recreate the view for, the next present retargets.
+            if result.is_err() {
+                return Ok(result);
+            }
 
-            let backbuffer: ID3D11Texture2D = expect!(
-                swap_chain.GetBuffer(0),
-                "Failed to get swapchain's backbuffer"
-            );
-
-            let device: ID3D11Device =
-                expect!(swap_chain.GetDevice(), "Failed to get swapchain's device");
-
-            let new_view = expect!(
-                device.CreateRenderTargetView(backbuffer, 0 as _),
-                "Failed to create render target view"
-            );
+            this.backbuffer_size = objects.create_render_view(swap_chain)?;
+            this.input_collector
+                .set_backbuffer_size(Vec2::new(this.backbuffer_size.0, this.backbuffer_size.1));
+            // Buffers might have been resized into another format.
+            Self::update_color_mode(this, swap_chain, self.color_mode)?;
 
-            this.render_view = Some(new_view);
-            result
+            Ok(result)
         }
     }
 
@@ -320,31 +483,180 @@ impl<T> DirectX11App<T> {
     /// `false` otherwise.
     #[inline]
     pub fn wnd_proc(&self, umsg: u32, wparam: WPARAM, lparam: LPARAM) -> InputResult {
-        self.lock_data()
-            .input_collector
-            .process(umsg, wparam.0, lparam.0)
+        let this = &mut *self.lock_data();
+        let input = this.process_message(umsg, wparam, lparam);
+        self.visible
+            .store(this.input_collector.is_visible(), Ordering::Relaxed);
+
+        input
     }
-}
 
-impl<T> DirectX11App<T> {
-    #[inline]
-    fn get_screen_size(&self) -> (f32, f32) {
-        let mut rect = RECT::default();
-        unsafe {
-            GetClientRect(
-                expect!(self.hwnd.get(), "You need to call init first"),
-                &mut rect,
-            );
+    /// Same as [`Self::wnd_proc`], but also decides if the message should reach the game.
+    /// Decision is made by [`CaptureMode`] set with [`Self::set_capture_mode`].
+    pub fn wnd_proc_filtered(&self, umsg: u32, wparam: WPARAM, lparam: LPARAM) -> WndProcResult {
+        let this = &mut *self.lock_data();
+        let input = this.process_message(umsg, wparam, lparam);
+        self.visible
+            .store(this.input_collector.is_visible(), Ordering::Relaxed);
+
+        if !this.input_collector.is_visible() && !matches!(input, InputResult::Toggle) {
+            return WndProcResult::PassThrough;
         }
-        (
-            (rect.right - rect.left) as f32,
-            (rect.bottom - rect.top) as f32,
+
+        this.capture_mode.filter(
+            &input,
+            is_release(umsg),
+            this.ctx.wants_pointer_input(),
+            this.ctx.wants_keyboard_input(),
         )
     }
 
+    /// Sets how [`Self::wnd_proc_filtered`] treats messages. Defaults to [`CaptureMode::WhenWanted`].
+    /// Safe to call from inside of the ui, change is applied by the next [`Self::present`] or [`Self::wnd_proc`].
+    #[inline]
+    pub fn set_capture_mode(&self, mode: CaptureMode) {
+        self.pending.lock().capture_mode = Some(mode);
+    }
+
+    /// Sets key combination that shows and hides the overlay, `None` disables it.
+    /// Toggle key is handled by [`Self::wnd_proc`] and never reaches egui.
+    /// Safe to call from inside of the ui, e.g. from a key binding menu.
+    #[inline]
+    pub fn set_toggle_key(&self, toggle: Option<Hotkey>) {
+        self.pending.lock().toggle_key = Some(toggle);
+    }
+
+    /// Checks if the overlay is currently shown, or is going to be after [`Self::set_visible`].
+    /// Safe to call from inside of the ui.
+    #[inline]
+    pub fn is_visible(&self) -> bool {
+        self.pending
+            .lock()
+            .visible
+            .unwrap_or_else(|| self.visible.load(Ordering::Relaxed))
+    }
+
+    /// Shows or hides the overlay. While hidden, [`Self::present`] doesn't run egui at all.
+    /// Safe to call from inside of the ui, e.g. from a "Close" button.
+    /// Change is applied by the next [`Self::present`] or [`Self::wnd_proc`].
+    #[inline]
+    pub fn set_visible(&self, visible: bool) {
+        self.pending.lock().visible = Some(visible);
+    }
+
+    /// Sets closure which is called by [`Self::present`] instead of the ui while the overlay is hidden.
+    /// Safe to call from inside of the ui or the background closure.
+    #[inline]
+    pub fn set_background(&self, background: impl FnMut(&mut T) + 'static) {
+        self.pending.lock().background = Some(Box::new(background));
+    }
+
+    /// Sets what happens with links opened by the ui. Defaults to [`ShellUrlOpener`].
+    /// Safe to call from inside of the ui.
+    #[inline]
+    pub fn set_url_opener(&self, opener: impl UrlOpener + 'static) {
+        self.pending.lock().url_opener = Some(Box::new(opener));
+    }
+
+    /// Sets closure which decides if the link should be passed to the [`UrlOpener`].
+    /// Can be used as an allow-list or to ask the user for confirmation.
+    /// Only `http` and `https` links are allowed by default, see [`is_web_url`].
+    /// Safe to call from inside of the ui.
+    #[inline]
+    pub fn set_url_filter(&self, filter: impl FnMut(&OpenUrl) -> bool + 'static) {
+        self.pending.lock().url_filter = Some(Box::new(filter));
+    }
+
+    /// Sets how mouse positions are mapped from the window onto the backbuffer.
+    /// Defaults to [`CoordinateMapping::Stretch`].
+    /// Safe to call from inside of the ui.
+    /// Change is applied by the next [`Self::present`] or [`Self::wnd_proc`].
+    #[inline]
+    pub fn set_coordinate_mapping(&self, mapping: CoordinateMapping) {
+        self.pending.lock().coordinate_mapping = Some(mapping);
+    }
+
+    /// Overrides scale of the ui, `None` uses the window's DPI. Defaults to `None`.
+    /// Safe to call from inside of the ui, e.g. from a scale slider.
+    /// Change is applied by the next [`Self::present`] or [`Self::wnd_proc`].
+    #[inline]
+    pub fn set_pixels_per_point(&self, pixels_per_point: Option<f32>) {
+        self.pending.lock().pixels_per_point = Some(pixels_per_point);
+    }
+
+    /// Whether held modifiers should be re-read with `GetKeyState` when the window gains focus.
+    /// Enabled by default. Safe to call from inside of the ui.
+    /// Change is applied by the next [`Self::present`] or [`Self::wnd_proc`].
+    #[inline]
+    pub fn set_resync_modifiers(&self, resync: bool) {
+        self.pending.lock().resync_modifiers = Some(resync);
+    }
+
+    /// Sets how mip levels of `tid` are made, see [`MipMode`]. Defaults to [`MipMode::Off`].
+    /// Can be called before egui allocates the texture, otherwise it's uploaded again on the next present.
+    /// Safe to call from inside of the ui, e.g. right after loading an image.
     #[inline]
-    fn get_viewport(&self) -> D3D11_VIEWPORT {
-        let (w, h) = self.get_screen_size();
+    pub fn set_mip_mode(&self, tid: TextureId, mode: MipMode) {
+        self.pending.lock().mip_modes.push((tid, mode));
+    }
+
+    /// Sets whether egui's textures keep a CPU copy of their pixels, see [`TextureMemoryPolicy`].
+    /// Applies to textures allocated afterwards, font atlas keeps its pixels under any policy.
+    /// Safe to call from inside of the ui, change is applied by the next [`Self::present`].
+    #[inline]
+    pub fn set_texture_memory_policy(&self, policy: TextureMemoryPolicy) {
+        self.pending.lock().memory_policy = Some(policy);
+    }
+
+    /// Memory used by egui's textures as of the last [`Self::present`] that drew the ui.
+    /// Safe to call from inside of the ui.
+    #[inline]
+    pub fn texture_memory_stats(&self) -> TextureMemoryStats {
+        self.texture_stats.lock().unwrap_or_default()
+    }
+
+    /// Lets egui draw a view owned by the game, like a render target of a scope camera.
+    /// Returned [`TextureId::User`] can be passed to [`egui::Image`] and friends.
+    /// View has to be created from the game's device. Once the device changes or [`Self::reset`]
+    /// is called the view is dropped, but the id stays valid for [`Self::update_native_texture`].
+    /// Native texture methods are safe to call from inside of the ui.
+    #[inline]
+    pub fn register_native_texture(&self, view: ID3D11ShaderResourceView) -> TextureId {
+        self.native.lock().register(view)
+    }
+
+    /// Replaces the view behind `tid`, e.g. after the game has recreated its render target.
+    #[inline]
+    pub fn update_native_texture(
+        &self,
+        tid: TextureId,
+        view: ID3D11ShaderResourceView,
+    ) -> Result<()> {
+        self.native.lock().update(tid, view)
+    }
+
+    /// Forgets the view behind `tid`, egui shouldn't draw it afterwards.
+    #[inline]
+    pub fn free_native_texture(&self, tid: TextureId) -> Result<()> {
+        self.native.lock().free(tid)
+    }
+
+    /// Releases everything created from the game's device, textures keep their pixels.
+    /// Call it once the game reports `DXGI_ERROR_DEVICE_REMOVED`, or before it recreates the device.
+    /// Next [`Self::present`] rebuilds everything from the swapchain it gets.
+    /// Native textures are dropped too, give them new views with [`Self::update_native_texture`].
+    pub fn reset(&self) {
+        let this = &mut *self.lock_data();
+        this.objects = None;
+        this.tex_alloc.release();
+        self.native.lock().mark_stale();
+        *self.texture_stats.lock() = Some(this.tex_alloc.memory_stats());
+    }
+}
+
+impl<T> DirectX11App<T> {
+    #[inline]
+    fn get_viewport((w, h): (f32, f32)) -> D3D11_VIEWPORT {
         D3D11_VIEWPORT {
             TopLeftX: 0.,
             TopLeftY: 0.,
@@ -355,82 +667,214 @@ impl<T> DirectX11App<T> {
         }
     }
 
-    fn set_blend_state(&self, dev: &ID3D11Device, ctx: &ID3D11DeviceContext) {
-        let mut targets: [D3D11_RENDER_TARGET_BLEND_DESC; 8] = Default::default();
-        targets[0].BlendEnable = true.into();
-        targets[0].SrcBlend = D3D11_BLEND_SRC_ALPHA;
-        targets[0].DestBlend = D3D11_BLEND_INV_SRC_ALPHA;
-        targets[0].BlendOp = D3D11_BLEND_OP_ADD;
-        targets[0].SrcBlendAlpha = D3D11_BLEND_ONE;
-        targets[0].DestBlendAlpha = D3D11_BLEND_INV_SRC_ALPHA;
-        targets[0].BlendOpAlpha = D3D11_BLEND_OP_ADD;
-        targets[0].RenderTargetWriteMask = D3D11_COLOR_WRITE_ENABLE_ALL.0 as _;
-
-        let blend_desc = D3D11_BLEND_DESC {
-            AlphaToCoverageEnable: false.into(),
-            IndependentBlendEnable: false.into(),
-            RenderTarget: targets,
+    /// Runs the ui and draws its output, game's state is saved by the caller.
+    #[allow(clippy::cast_ref_to_mut)]
+    unsafe fn render(
+        &self,
+        this: &mut AppData<T>,
+        dev: &ID3D11Device,
+        ctx: &ID3D11DeviceContext,
+    ) -> Result<()> {
+        let screen = this.backbuffer_size;
+        let screen_viewport = Self::get_viewport(screen);
+        let objects = expect!(this.objects.as_mut(), "Device objects weren't created");
+
+        if cfg!(feature = "clear") {
+            ctx.ClearRenderTargetView(&objects.render_view, [0.39, 0.58, 0.92, 1.].as_ptr());
+        }
+
+        // Before running the ui, so a failed restore doesn't throw away its textures delta.
+        this.tex_alloc.restore(dev, ctx)?;
+
+        let output = this.ctx.run(this.input_collector.collect_input(), |ctx| {
+            // Dont look here, it should be fine until someone tries to do something horrible.
+            (this.ui)(ctx, &mut this.state);
+        });
+
+        // Unknown and empty textures don't stop the frame, they are reported once it's drawn.
+        let deltas = match this
+            .tex_alloc
+            .process_deltas(dev, ctx, output.textures_delta)
+        {
+            Err(e @ (Error::UnknownTexture(_) | Error::EmptyTexture(_))) => Err(e),
+            Err(e) => return Err(e),
+            Ok(()) => Ok(()),
         };
 
-        unsafe {
-            let blend_state = expect!(
-                dev.CreateBlendState(&blend_desc),
-                "Failed to create blend state"
-            );
-            ctx.OMSetBlendState(&blend_state, [0., 0., 0., 0.].as_ptr(), 0xffffffff);
+        let ppp = this.ctx.pixels_per_point();
+        this.cursor_icon = output.platform_output.cursor_icon;
+        this.input_collector
+            .set_text_cursor_pos(output.platform_output.text_cursor_pos);
+
+        if let Some(url) = output.platform_output.open_url {
+            open_url(&url, &mut this.url_filter, this.url_opener.as_mut());
         }
-    }
 
-    fn set_raster_options(&self, dev: &ID3D11Device, ctx: &ID3D11DeviceContext) {
-        let raster_desc = D3D11_RASTERIZER_DESC {
-            FillMode: D3D11_FILL_SOLID,
-            CullMode: D3D11_CULL_NONE,
-            FrontCounterClockwise: false.into(),
-            DepthBias: false.into(),
-            DepthBiasClamp: 0.,
-            SlopeScaledDepthBias: 0.,
-            DepthClipEnable: false.into(),
-            ScissorEnable: true.into(),
-            MultisampleEnable: false.into(),
-            AntialiasedLineEnable: false.into(),
-        };
+        if !output.platform_output.copied_text.is_empty() {
+            this.input_collector
+                .set_clipboard_text(output.platform_output.copied_text);
+        }
 
-        unsafe {
-            let options = expect!(
-                dev.CreateRasterizerState(&raster_desc),
-                "Failed to create rasterizer state"
-            );
-            ctx.RSSetState(&options);
+        if output.shapes.is_empty() {
+            return deltas;
         }
+
+        let mut meshes = vec![];
+        let mut commands = vec![];
+        for ClippedPrimitive {
+            clip_rect,
+            primitive,
+        } in this.ctx.tessellate(output.shapes)
+        {
+            match primitive {
+                Primitive::Mesh(mesh) => {
+                    if let Some(mesh) =
+                        GpuMesh::from_mesh(screen, ppp, mesh, clip_rect, this.color_mode)
+                    {
+                        commands.push(DrawCommand::Mesh(meshes.len()));
+                        meshes.push(mesh);
+                    }
+                }
+                Primitive::Callback(callback) => {
+                    commands.push(DrawCommand::Callback(clip_rect, callback));
+                }
+            }
+        }
+
+        let ranges = objects.buffers.upload(dev, ctx, &meshes)?;
+
+        objects.bind(ctx, screen_viewport);
+
+        for command in commands {
+            match command {
+                DrawCommand::Mesh(i) => {
+                    let (mesh, range) = (&meshes[i], ranges[i]);
+                    let scissor = match clip_rect_to_scissor(mesh.clip, screen, ppp) {
+                        Some(scissor) => scissor,
+                        None => continue,
+                    };
+                    let texture = match mesh.texture_id {
+                        TextureId::User(_) => self.native.lock().get(mesh.texture_id),
+                        tid => this.tex_alloc.get_by_id(tid),
+                    };
+                    let filter = this
+                        .tex_alloc
+                        .get_filter(mesh.texture_id)
+                        .unwrap_or(TextureFilter::Linear);
+
+                    ctx.RSSetScissorRects(&[scissor]);
+                    objects
+                        .samplers
+                        .bind(&objects.device, ctx, SamplerKey::new(filter))?;
+
+                    if texture.is_some() {
+                        ctx.PSSetShaderResources(0, &[texture]);
+                    }
+
+                    ctx.DrawIndexed(range.index_count, range.start_index, range.base_vertex);
+                }
+                DrawCommand::Callback(clip_rect, callback) => {
+                    // Callbacks made for other backends are silently skipped.
+                    let f = match callback.callback.downcast_ref::<D3D11CallbackFn>() {
+                        Some(f) => f,
+                        None => continue,
+                    };
+
+                    let scissor = match clip_rect_to_scissor(clip_rect, screen, ppp) {
+                        Some(scissor) => scissor,
+                        None => continue,
+                    };
+                    let clip_rect = Rect::from_min_max(
+                        Pos2::new(scissor.left as _, scissor.top as _),
+                        Pos2::new(scissor.right as _, scissor.bottom as _),
+                    );
+                    let viewport = D3D11_VIEWPORT {
+                        TopLeftX: callback.rect.min.x * ppp,
+                        TopLeftY: callback.rect.min.y * ppp,
+                        Width: callback.rect.width() * ppp,
+                        Height: callback.rect.height() * ppp,
+                        MinDepth: 0.,
+                        MaxDepth: 1.,
+                    };
+
+                    ctx.RSSetViewports(&[viewport]);
+                    ctx.RSSetScissorRects(&[scissor]);
+
+                    f.call(&CallbackInfo {
+                        device: dev,
+                        context: ctx,
+                        clip_rect,
+                        viewport,
+                    });
+
+                    // Callback is free to mess with the pipeline, bring ours back.
+                    objects.bind(ctx, screen_viewport);
+                }
+            }
+        }
+
+        deltas
     }
 
-    fn set_sampler_state(&self, dev: &ID3D11Device, ctx: &ID3D11DeviceContext) {
-        let desc = D3D11_SAMPLER_DESC {
-            Filter: D3D11_FILTER_MIN_MAG_MIP_LINEAR,
-            AddressU: D3D11_TEXTURE_ADDRESS_BORDER,
-            AddressV: D3D11_TEXTURE_ADDRESS_BORDER,
-            AddressW: D3D11_TEXTURE_ADDRESS_BORDER,
-            MipLODBias: 0.,
-            ComparisonFunc: D3D11_COMPARISON_ALWAYS,
-            MinLOD: 0.,
-            MaxLOD: 0.,
-            BorderColor: [1., 1., 1., 1.],
-            ..Default::default()
-        };
+    /// Recreates everything owned by the device, textures are uploaded again by [`Self::render`].
+    fn rebuild(
+        this: &mut AppData<T>,
+        swap: &IDXGISwapChain,
+        dev: &ID3D11Device,
+        color_mode: ColorMode,
+    ) -> Result<()> {
+        this.objects = None;
+        this.tex_alloc.release();
+
+        let mut objects = DeviceObjects::new(dev.clone())?;
+        this.backbuffer_size = objects.create_render_view(swap)?;
+        this.input_collector
+            .set_backbuffer_size(Vec2::new(this.backbuffer_size.0, this.backbuffer_size.1));
+
+        this.objects = Some(objects);
+        Self::update_color_mode(this, swap, color_mode)
+    }
 
-        unsafe {
-            let sampler = expect!(dev.CreateSamplerState(&desc), "Failed to create sampler");
-            ctx.PSSetSamplers(0, &[Some(sampler)]);
+    /// Points the render view at the backbuffer of a new swapchain made by the same device.
+    fn retarget(this: &mut AppData<T>, swap: &IDXGISwapChain, color_mode: ColorMode) -> Result<()> {
+        let objects = expect!(this.objects.as_mut(), "Device objects weren't created");
+        drop(objects.render_view.take());
+
+        this.backbuffer_size = objects.create_render_view(swap)?;
+        this.input_collector
+            .set_backbuffer_size(Vec2::new(this.backbuffer_size.0, this.backbuffer_size.1));
+
+        Self::update_color_mode(this, swap, color_mode)
+    }
+
+    /// Resolves `color_mode` against `swap`'s format again, textures are uploaded again if it changes.
+    fn update_color_mode(
+        this: &mut AppData<T>,
+        swap: &IDXGISwapChain,
+        color_mode: ColorMode,
+    ) -> Result<()> {
+        let desc = unsafe { swap.GetDesc() }
+            .map_err(|e| Error::Device("Failed to get swapchain's descriptor", e))?;
+        let color_mode = color_mode.resolve(desc.BufferDesc.Format);
+
+        if color_mode != this.color_mode {
+            this.color_mode = color_mode;
+            this.tex_alloc.set_color_mode(color_mode);
         }
+
+        Ok(())
     }
 }
 
-unsafe fn get_device_and_context(swap: &IDXGISwapChain) -> (ID3D11Device, ID3D11DeviceContext) {
-    let device: ID3D11Device = expect!(swap.GetDevice(), "Failed to get swapchain's device");
+unsafe fn get_device_and_context(
+    swap: &IDXGISwapChain,
+) -> Result<(ID3D11Device, ID3D11DeviceContext)> {
+    let device: ID3D11Device = swap
+        .GetDevice()
+        .map_err(|e| Error::Device("Failed to get swapchain's device", e))?;
     let mut ctx = None;
     device.GetImmediateContext(&mut ctx);
-    (
-        device,
-        expect!(ctx, "Failed to get device's immediate context"),
-    )
+    let ctx = expect!(ctx, "Failed to get device's immediate context");
+
+    Ok((device, ctx))
 }
@@ -0,0 +1,137 @@
+//! Sizing and placement of meshes inside of the shared buffers.
+//! Doesn't touch Direct3D at all.
+
+/// Amount of elements buffers are created with when nothing was allocated yet.
+pub const MIN_CAPACITY: usize = 1024;
+
+/// Computes capacity a buffer must be grown to in order to fit `required` elements.
+/// Capacity doubles until it's big enough, so reallocations stay rare.
+/// Returns `None` if `current` capacity is already sufficient.
+pub fn grow_capacity(current: usize, required: usize) -> Option<usize> {
+    if required <= current {
+        return None;
+    }
+
+    let mut capacity = current.max(MIN_CAPACITY);
+    while capacity < required {
+        capacity = capacity.saturating_mul(2);
+    }
+
+    Some(capacity)
+}
+
+/// Location of a single mesh inside of the shared vertex and index buffers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MeshRange {
+    /// Added to every index of the mesh by the input assembler.
+    pub base_vertex: i32,
+    pub start_index: u32,
+    pub index_count: u32,
+}
+
+/// Packs meshes of a single frame one after another.
+#[derive(Debug, Default)]
+pub struct FrameLayout {
+    vertices: usize,
+    indices: usize,
+}
+
+impl FrameLayout {
+    /// Reserves space for the next mesh and returns where it's going to be located.
+    pub fn push(&mut self, vertices: usize, indices: usize) -> MeshRange {
+        let range = MeshRange {
+            base_vertex: self.vertices as _,
+            start_index: self.indices as _,
+            index_count: indices as _,
+        };
+
+        self.vertices += vertices;
+        self.indices += indices;
+        range
+    }
+
+    #[inline]
+    pub fn vertex_count(&self) -> usize {
+        self.vertices
+    }
+
+    #[inline]
+    pub fn index_count(&self) -> usize {
+        self.indices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grow_capacity_keeps_sufficient_buffers() {
+        assert_eq!(grow_capacity(MIN_CAPACITY, 10), None);
+        assert_eq!(grow_capacity(4096, 4096), None);
+        assert_eq!(grow_capacity(0, 0), None);
+    }
+
+    #[test]
+    fn grow_capacity_starts_at_min_capacity() {
+        assert_eq!(grow_capacity(0, 1), Some(MIN_CAPACITY));
+        assert_eq!(grow_capacity(0, MIN_CAPACITY), Some(MIN_CAPACITY));
+    }
+
+    #[test]
+    fn grow_capacity_doubles_past_required() {
+        assert_eq!(grow_capacity(0, MIN_CAPACITY + 1), Some(MIN_CAPACITY * 2));
+        assert_eq!(grow_capacity(MIN_CAPACITY, 3000), Some(4096));
+        assert_eq!(grow_capacity(3000, 3001), Some(6000));
+    }
+
+    #[test]
+    fn grow_capacity_saturates() {
+        let half = 1 << (usize::BITS - 1);
+        assert_eq!(grow_capacity(half, usize::MAX), Some(usize::MAX));
+    }
+
+    #[test]
+    fn frame_layout_places_meshes_one_after_another() {
+        let mut layout = FrameLayout::default();
+
+        let ranges = [layout.push(4, 6), layout.push(3, 3), layout.push(10, 24)];
+
+        assert_eq!(
+            ranges,
+            [
+                MeshRange {
+                    base_vertex: 0,
+                    start_index: 0,
+                    index_count: 6,
+                },
+                MeshRange {
+                    base_vertex: 4,
+                    start_index: 6,
+                    index_count: 3,
+                },
+                MeshRange {
+                    base_vertex: 7,
+                    start_index: 9,
+                    index_count: 24,
+                },
+            ]
+        );
+        assert_eq!(layout.vertex_count(), 17);
+        assert_eq!(layout.index_count(), 33);
+    }
+
+    #[test]
+    fn frame_layout_empty_mesh_takes_no_space() {
+        let mut layout = FrameLayout::default();
+
+        layout.push(4, 6);
+        let empty = layout.push(0, 0);
+        let next = layout.push(3, 3);
+
+        assert_eq!(empty.index_count, 0);
+        assert_eq!((empty.base_vertex, empty.start_index), (4, 6));
+        assert_eq!((next.base_vertex, next.start_index), (4, 6));
+        assert_eq!((layout.vertex_count(), layout.index_count()), (7, 9));
+    }
+}
@@ -0,0 +1,121 @@
+mod layout;
+pub use layout::MeshRange;
+
+use crate::{
+    error::{Error, Result},
+    mesh::{GpuMesh, GpuVertex},
+};
+use layout::{grow_capacity, FrameLayout};
+use std::{mem::size_of, ptr::null};
+use windows::Win32::Graphics::{
+    Direct3D11::{
+        ID3D11Buffer, ID3D11Device, ID3D11DeviceContext, D3D11_BIND_FLAG, D3D11_BIND_INDEX_BUFFER,
+        D3D11_BIND_VERTEX_BUFFER, D3D11_BUFFER_DESC, D3D11_CPU_ACCESS_WRITE,
+        D3D11_MAP_WRITE_DISCARD, D3D11_USAGE_DYNAMIC,
+    },
+    Dxgi::Common::DXGI_FORMAT_R32_UINT,
+};
+
+/// Vertex and index buffers shared by every mesh of a frame.
+/// Buffers are rewritten with `WRITE_DISCARD` each frame and only reallocated
+/// when the frame doesn't fit into them anymore.
+#[derive(Default)]
+pub struct MeshBuffers {
+    vertex: Option<ID3D11Buffer>,
+    vertex_capacity: usize,
+    index: Option<ID3D11Buffer>,
+    index_capacity: usize,
+}
+
+impl MeshBuffers {
+    /// Writes all meshes into the buffers, growing them if needed.
+    /// Returns location of each mesh, in the same order as `meshes`.
+    pub fn upload(
+        &mut self,
+        dev: &ID3D11Device,
+        ctx: &ID3D11DeviceContext,
+        meshes: &[GpuMesh],
+    ) -> Result<Vec<MeshRange>> {
+        let mut layout = FrameLayout::default();
+        let ranges = meshes
+            .iter()
+            .map(|mesh| layout.push(mesh.vertices.len(), mesh.indices.len()))
+            .collect::<Vec<_>>();
+
+        if layout.index_count() == 0 {
+            return Ok(ranges);
+        }
+
+        if let Some(capacity) = grow_capacity(self.vertex_capacity, layout.vertex_count()) {
+            self.vertex = Some(create_buffer(
+                dev,
+                capacity * size_of::<GpuVertex>(),
+                D3D11_BIND_VERTEX_BUFFER,
+            )?);
+            self.vertex_capacity = capacity;
+        }
+
+        if let Some(capacity) = grow_capacity(self.index_capacity, layout.index_count()) {
+            self.index = Some(create_buffer(
+                dev,
+                capacity * size_of::<u32>(),
+                D3D11_BIND_INDEX_BUFFER,
+            )?);
+            self.index_capacity = capacity;
+        }
+
+        unsafe {
+            let vertex = expect!(self.vertex.as_ref(), "Vertex buffer wasn't allocated");
+            let vertices = ctx
+                .Map(vertex, 0, D3D11_MAP_WRITE_DISCARD, 0)
+                .map_err(|e| Error::Buffer("Failed to map vertex buffer", e))?
+                .pData as *mut GpuVertex;
+
+            let index = expect!(self.index.as_ref(), "Index buffer wasn't allocated");
+            let indices = match ctx.Map(index, 0, D3D11_MAP_WRITE_DISCARD, 0) {
+                Ok(subresource) => subresource.pData as *mut u32,
+                Err(e) => {
+                    ctx.Unmap(vertex, 0);
+                    return Err(Error::Buffer("Failed to map index buffer", e));
+                }
+            };
+
+            for (mesh, range) in meshes.iter().zip(&ranges) {
+                vertices
+                    .add(range.base_vertex as usize)
+                    .copy_from_nonoverlapping(mesh.vertices.as_ptr(), mesh.vertices.len());
+                indices
+                    .add(range.start_index as usize)
+                    .copy_from_nonoverlapping(mesh.indices.as_ptr(), mesh.indices.len());
+            }
+
+            ctx.Unmap(vertex, 0);
+            ctx.Unmap(index, 0);
+        }
+
+        Ok(ranges)
+    }
+
+    /// Binds buffers to the input assembler.
+    pub fn bind(&self, ctx: &ID3D11DeviceContext) {
+        unsafe {
+            ctx.IASetVertexBuffers(0, 1, &self.vertex, &(size_of::<GpuVertex>() as _), &0);
+            ctx.IASetIndexBuffer(self.index.clone(), DXGI_FORMAT_R32_UINT, 0);
+        }
+    }
+}
+
+fn create_buffer(dev: &ID3D11Device, size: usize, bind: D3D11_BIND_FLAG) -> Result<ID3D11Buffer> {
+    let desc = D3D11_BUFFER_DESC {
+        ByteWidth: size as _,
+        Usage: D3D11_USAGE_DYNAMIC,
+        BindFlags: bind.0,
+        CPUAccessFlags: D3D11_CPU_ACCESS_WRITE.0,
+        ..Default::default()
+    };
+
+    unsafe {
+        dev.CreateBuffer(&desc, null())
+            .map_err(|e| Error::Buffer("Failed to create mesh buffer", e))
+    }
+}
@@ -0,0 +1,31 @@
+use egui::Rect;
+use windows::Win32::Graphics::Direct3D11::{ID3D11Device, ID3D11DeviceContext, D3D11_VIEWPORT};
+
+/// Everything a [`D3D11CallbackFn`] gets to work with.
+pub struct CallbackInfo<'a> {
+    pub device: &'a ID3D11Device,
+    pub context: &'a ID3D11DeviceContext,
+    /// Clip rectangle of the callback in pixels. Already bound as the scissor rect.
+    pub clip_rect: Rect,
+    /// Viewport covering [`egui::PaintCallback::rect`]. Already bound.
+    pub viewport: D3D11_VIEWPORT,
+}
+
+/// D3D11 flavour of egui's paint callback.
+/// Put it into [`egui::PaintCallback::callback`] to render custom content inside of egui.
+/// Pipeline state of the backend is restored after the callback returns,
+/// so you are free to bind anything you want.
+pub struct D3D11CallbackFn {
+    f: Box<dyn Fn(&CallbackInfo) + Sync + Send>,
+}
+
+impl D3D11CallbackFn {
+    pub fn new(f: impl Fn(&CallbackInfo) + Sync + Send + 'static) -> Self {
+        Self { f: Box::new(f) }
+    }
+
+    #[inline]
+    pub(crate) fn call(&self, info: &CallbackInfo) {
+        (self.f)(info)
+    }
+}
@@ -0,0 +1,147 @@
+use crate::input::InputResult;
+
+/// Decides which window messages should be kept away from the game.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CaptureMode {
+    /// Every message is passed to the game.
+    PassThrough,
+    /// Mouse messages are blocked while egui wants pointer input,
+    /// keyboard messages are blocked while egui wants keyboard input.
+    #[default]
+    WhenWanted,
+    /// Every mouse and keyboard message is blocked, except for releases.
+    /// Meant to be used while your overlay is open.
+    BlockAll,
+}
+
+impl CaptureMode {
+    /// `wants_pointer` and `wants_keyboard` are egui's wishes from the last frame.
+    /// Releases of keys and mouse buttons always pass, the game might have seen the press
+    /// before egui took over, and would keep the key held otherwise.
+    pub fn filter(
+        self,
+        input: &InputResult,
+        released: bool,
+        wants_pointer: bool,
+        wants_keyboard: bool,
+    ) -> WndProcResult {
+        let consume = match self {
+            Self::PassThrough => false,
+            _ if released => false,
+            Self::WhenWanted => {
+                matches!(input, InputResult::Toggle | InputResult::Cursor)
+                    || (input.is_pointer() && wants_pointer)
+                    || (input.is_keyboard() && wants_keyboard)
+            }
+            Self::BlockAll => {
+                matches!(input, InputResult::Cursor) || input.is_pointer() || input.is_keyboard()
+            }
+        };
+
+        if consume {
+            WndProcResult::Consume
+        } else {
+            WndProcResult::PassThrough
+        }
+    }
+}
+
+/// What should happen to the window message after egui has seen it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WndProcResult {
+    /// Message shouldn't reach the game, return from your hook without calling the original procedure.
+    Consume,
+    /// Message should be passed to the original window procedure.
+    PassThrough,
+}
+
+impl WndProcResult {
+    #[inline]
+    pub fn is_consumed(&self) -> bool {
+        matches!(*self, WndProcResult::Consume)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODES: [CaptureMode; 3] = [
+        CaptureMode::PassThrough,
+        CaptureMode::WhenWanted,
+        CaptureMode::BlockAll,
+    ];
+
+    fn consumed(mode: CaptureMode, input: InputResult, pointer: bool, keyboard: bool) -> bool {
+        mode.filter(&input, false, pointer, keyboard).is_consumed()
+    }
+
+    #[test]
+    fn pass_through_never_consumes() {
+        for input in [
+            InputResult::MouseLeft,
+            InputResult::Key,
+            InputResult::Toggle,
+            InputResult::Cursor,
+        ] {
+            assert!(!consumed(CaptureMode::PassThrough, input, true, true));
+        }
+    }
+
+    #[test]
+    fn when_wanted_follows_egui() {
+        let mode = CaptureMode::WhenWanted;
+
+        assert!(consumed(mode, InputResult::MouseLeft, true, false));
+        assert!(consumed(mode, InputResult::Scroll, true, false));
+        assert!(!consumed(mode, InputResult::MouseLeft, false, true));
+
+        assert!(consumed(mode, InputResult::Key, false, true));
+        assert!(consumed(mode, InputResult::Character, false, true));
+        assert!(consumed(mode, InputResult::Composition, false, true));
+        assert!(!consumed(mode, InputResult::Key, true, false));
+
+        assert!(consumed(mode, InputResult::Toggle, false, false));
+        assert!(consumed(mode, InputResult::Cursor, false, false));
+        assert!(!consumed(mode, InputResult::Focus, true, true));
+        assert!(!consumed(mode, InputResult::Unknown, true, true));
+    }
+
+    #[test]
+    fn block_all_ignores_egui() {
+        let mode = CaptureMode::BlockAll;
+
+        for input in [
+            InputResult::MouseMove,
+            InputResult::MouseExtra,
+            InputResult::Zoom,
+            InputResult::Key,
+            InputResult::Character,
+            InputResult::Toggle,
+            InputResult::Cursor,
+        ] {
+            assert!(consumed(mode, input, false, false), "{:?}", input);
+        }
+
+        assert!(!consumed(mode, InputResult::Focus, false, false));
+        assert!(!consumed(mode, InputResult::MouseLeave, false, false));
+        assert!(!consumed(mode, InputResult::Unknown, false, false));
+    }
+
+    #[test]
+    fn releases_always_pass() {
+        for mode in MODES {
+            for input in [
+                InputResult::Key,
+                InputResult::Toggle,
+                InputResult::MouseLeft,
+                InputResult::MouseRight,
+                InputResult::MouseMiddle,
+                InputResult::MouseExtra,
+            ] {
+                let result = mode.filter(&input, true, true, true);
+                assert_eq!(result, WndProcResult::PassThrough, "{:?} {:?}", mode, input);
+            }
+        }
+    }
+}
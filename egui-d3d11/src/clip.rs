@@ -0,0 +1,166 @@
+use egui::Rect;
+use windows::Win32::Foundation::RECT;
+
+/// Converts egui's clip rect in points into a scissor rect in pixels.
+/// Clamping and rounding to the nearest pixel follow `egui_glow`. Returns `None` if nothing
+/// of the rect is left on the target or any of its edges is NaN.
+pub fn clip_rect_to_scissor(clip: Rect, (w, h): (f32, f32), pixels_per_point: f32) -> Option<RECT> {
+    let min_x = clip.min.x * pixels_per_point;
+    let min_y = clip.min.y * pixels_per_point;
+    let max_x = clip.max.x * pixels_per_point;
+    let max_y = clip.max.y * pixels_per_point;
+
+    // `f32::clamp` panics once NaN ends up as a bound.
+    if [min_x, min_y, max_x, max_y, w, h]
+        .iter()
+        .any(|v| v.is_nan())
+    {
+        return None;
+    }
+
+    let min_x = min_x.clamp(0., w);
+    let min_y = min_y.clamp(0., h);
+    let max_x = max_x.clamp(min_x, w);
+    let max_y = max_y.clamp(min_y, h);
+
+    let scissor = RECT {
+        left: min_x.round() as _,
+        top: min_y.round() as _,
+        right: max_x.round() as _,
+        bottom: max_y.round() as _,
+    };
+
+    if scissor.left >= scissor.right || scissor.top >= scissor.bottom {
+        None
+    } else {
+        Some(scissor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::Pos2;
+
+    const SCREEN: (f32, f32) = (1920., 1080.);
+
+    fn rect(min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> Rect {
+        Rect::from_min_max(Pos2::new(min_x, min_y), Pos2::new(max_x, max_y))
+    }
+
+    fn scissor(left: i32, top: i32, right: i32, bottom: i32) -> Option<RECT> {
+        Some(RECT {
+            left,
+            top,
+            right,
+            bottom,
+        })
+    }
+
+    #[test]
+    fn everything_covers_the_target() {
+        assert_eq!(
+            clip_rect_to_scissor(Rect::EVERYTHING, SCREEN, 1.),
+            scissor(0, 0, 1920, 1080)
+        );
+        assert_eq!(
+            clip_rect_to_scissor(Rect::EVERYTHING, SCREEN, 2.),
+            scissor(0, 0, 1920, 1080)
+        );
+        assert_eq!(clip_rect_to_scissor(Rect::NOTHING, SCREEN, 1.), None);
+    }
+
+    #[test]
+    fn scales_by_pixels_per_point() {
+        assert_eq!(
+            clip_rect_to_scissor(rect(10., 20., 110., 220.), SCREEN, 1.5),
+            scissor(15, 30, 165, 330)
+        );
+    }
+
+    #[test]
+    fn fractional_edges_are_rounded_to_nearest() {
+        assert_eq!(
+            clip_rect_to_scissor(rect(10.4, 20.6, 30.2, 40.5), SCREEN, 1.),
+            scissor(10, 21, 30, 41)
+        );
+        assert_eq!(
+            clip_rect_to_scissor(rect(0.25, 0.25, 0.75, 0.75), SCREEN, 1.),
+            scissor(0, 0, 1, 1)
+        );
+        assert_eq!(
+            clip_rect_to_scissor(rect(0.25, 0.25, 0.45, 0.45), SCREEN, 1.),
+            None
+        );
+    }
+
+    #[test]
+    fn nan_rects_are_dropped() {
+        assert_eq!(
+            clip_rect_to_scissor(rect(f32::NAN, 10., 100., 100.), SCREEN, 1.),
+            None
+        );
+        assert_eq!(
+            clip_rect_to_scissor(rect(10., 10., 100., f32::NAN), SCREEN, 1.),
+            None
+        );
+        assert_eq!(
+            clip_rect_to_scissor(rect(10., 10., 100., 100.), SCREEN, f32::NAN),
+            None
+        );
+        // Infinite edges times zero scale.
+        assert_eq!(clip_rect_to_scissor(Rect::EVERYTHING, SCREEN, 0.), None);
+    }
+
+    #[test]
+    fn negative_and_partially_visible_rects_are_clamped() {
+        assert_eq!(
+            clip_rect_to_scissor(rect(-50., -50., 100., 100.), SCREEN, 1.),
+            scissor(0, 0, 100, 100)
+        );
+        assert_eq!(
+            clip_rect_to_scissor(rect(1900., 1000., 2000., 1200.), SCREEN, 1.),
+            scissor(1900, 1000, 1920, 1080)
+        );
+    }
+
+    #[test]
+    fn off_screen_rects_are_dropped() {
+        assert_eq!(
+            clip_rect_to_scissor(rect(-200., -200., -100., -100.), SCREEN, 1.),
+            None
+        );
+        assert_eq!(
+            clip_rect_to_scissor(rect(-200., 10., -100., 100.), SCREEN, 1.),
+            None
+        );
+        assert_eq!(
+            clip_rect_to_scissor(rect(2000., 10., 2100., 100.), SCREEN, 1.),
+            None
+        );
+        assert_eq!(
+            clip_rect_to_scissor(rect(10., 1100., 100., 1200.), SCREEN, 1.),
+            None
+        );
+    }
+
+    #[test]
+    fn zero_area_clips_are_dropped() {
+        assert_eq!(
+            clip_rect_to_scissor(rect(10., 10., 10., 100.), SCREEN, 1.),
+            None
+        );
+        assert_eq!(
+            clip_rect_to_scissor(rect(10., 10., 100., 10.), SCREEN, 1.),
+            None
+        );
+        assert_eq!(
+            clip_rect_to_scissor(rect(100., 100., 10., 10.), SCREEN, 1.),
+            None
+        );
+        assert_eq!(
+            clip_rect_to_scissor(rect(10., 10., 100., 100.), (0., 0.), 1.),
+            None
+        );
+    }
+}
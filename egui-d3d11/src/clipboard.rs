@@ -0,0 +1,119 @@
+//! Clipboard used by copy, cut and paste inside of the ui.
+use crate::decode::{clipboard_action, ClipboardAction};
+use egui::{Event, Key, Modifiers};
+use std::{cell::RefCell, rc::Rc};
+
+/// Storage for text copied from and pasted into the ui.
+pub trait ClipboardBackend {
+    /// Returns current text, `None` if clipboard is empty or holds something else.
+    fn get(&mut self) -> Option<String>;
+    fn set(&mut self, text: String);
+}
+
+/// System wide clipboard, shared with the game and every other application.
+#[cfg(windows)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WindowsClipboard;
+
+#[cfg(windows)]
+impl ClipboardBackend for WindowsClipboard {
+    fn get(&mut self) -> Option<String> {
+        use ::clipboard::{windows_clipboard::WindowsClipboardContext, ClipboardProvider};
+
+        WindowsClipboardContext.get_contents().ok()
+    }
+
+    fn set(&mut self, text: String) {
+        use ::clipboard::{windows_clipboard::WindowsClipboardContext, ClipboardProvider};
+
+        let _ = WindowsClipboardContext.set_contents(text);
+    }
+}
+
+/// Clipboard that lives inside of the process and is never seen by the system.
+/// All clones share the same contents.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryClipboard {
+    text: Rc<RefCell<Option<String>>>,
+}
+
+impl MemoryClipboard {
+    /// Creates clipboard already holding `text`.
+    pub fn with_text(text: impl Into<String>) -> Self {
+        Self {
+            text: Rc::new(RefCell::new(Some(text.into()))),
+        }
+    }
+}
+
+impl ClipboardBackend for MemoryClipboard {
+    fn get(&mut self) -> Option<String> {
+        self.text.borrow().clone()
+    }
+
+    fn set(&mut self, text: String) {
+        *self.text.borrow_mut() = Some(text);
+    }
+}
+
+/// Clipboard used when none was given at init.
+pub fn default_clipboard() -> Box<dyn ClipboardBackend> {
+    #[cfg(windows)]
+    return Box::new(WindowsClipboard);
+
+    #[cfg(not(windows))]
+    return Box::new(MemoryClipboard::default());
+}
+
+/// Text event pasting clipboard's contents if `key` with `modifiers` is a paste shortcut.
+pub fn paste_event(
+    key: Key,
+    modifiers: Modifiers,
+    clipboard: &mut dyn ClipboardBackend,
+) -> Option<Event> {
+    match clipboard_action(key, modifiers)? {
+        ClipboardAction::Paste => clipboard.get().map(Event::Text),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CTRL: Modifiers = Modifiers {
+        alt: false,
+        ctrl: true,
+        shift: false,
+        mac_cmd: false,
+        command: true,
+    };
+
+    #[test]
+    fn ctrl_v_pastes_from_clipboard() {
+        let mut clipboard = MemoryClipboard::with_text("pasted");
+
+        assert_eq!(
+            paste_event(Key::V, CTRL, &mut clipboard),
+            Some(Event::Text("pasted".into()))
+        );
+        assert_eq!(
+            paste_event(Key::Insert, Modifiers::SHIFT, &mut clipboard),
+            Some(Event::Text("pasted".into()))
+        );
+        assert_eq!(paste_event(Key::V, Modifiers::NONE, &mut clipboard), None);
+        assert_eq!(paste_event(Key::C, CTRL, &mut clipboard), None);
+    }
+
+    #[test]
+    fn empty_clipboard_pastes_nothing() {
+        let mut clipboard = MemoryClipboard::default();
+        assert_eq!(paste_event(Key::V, CTRL, &mut clipboard), None);
+
+        clipboard.clone().set("later".into());
+        assert_eq!(
+            paste_event(Key::V, CTRL, &mut clipboard),
+            Some(Event::Text("later".into()))
+        );
+    }
+}
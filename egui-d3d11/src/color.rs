@@ -0,0 +1,68 @@
+use egui::{Color32, Rgba};
+use windows::Win32::Graphics::Dxgi::Common::{
+    DXGI_FORMAT, DXGI_FORMAT_B8G8R8A8_UNORM_SRGB, DXGI_FORMAT_B8G8R8X8_UNORM_SRGB,
+    DXGI_FORMAT_R8G8B8A8_UNORM, DXGI_FORMAT_R8G8B8A8_UNORM_SRGB,
+};
+
+/// How colors are written into the game's backbuffer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Pick [`ColorMode::Srgb`] if the swapchain uses one of the `DXGI_FORMAT_*_SRGB` formats,
+    /// [`ColorMode::Linear`] otherwise.
+    #[default]
+    Detect,
+    /// Backbuffer stores values as is, e.g. `DXGI_FORMAT_R8G8B8A8_UNORM`.
+    /// egui's gamma encoded colors are passed through untouched and blended in gamma space.
+    Linear,
+    /// Backbuffer encodes written values into sRGB by itself.
+    /// Vertex colors are linearized and textures are sampled through `_SRGB` views,
+    /// so blending happens in linear space.
+    Srgb,
+}
+
+impl ColorMode {
+    /// Resolves [`ColorMode::Detect`] against the swapchain's format.
+    pub fn resolve(self, backbuffer: DXGI_FORMAT) -> Self {
+        match self {
+            Self::Detect if is_srgb_format(backbuffer) => Self::Srgb,
+            Self::Detect => Self::Linear,
+            mode => mode,
+        }
+    }
+
+    /// Converts egui's color into what vertex shader expects.
+    #[inline]
+    pub fn vertex_color(self, color: Color32) -> Rgba {
+        match self {
+            Self::Srgb => color.into(),
+            _ => {
+                let [r, g, b, a] = color.to_array();
+                Rgba::from_rgba_premultiplied(
+                    r as f32 / 255.,
+                    g as f32 / 255.,
+                    b as f32 / 255.,
+                    a as f32 / 255.,
+                )
+            }
+        }
+    }
+
+    /// Format of textures allocated for egui.
+    #[inline]
+    pub fn texture_format(self) -> DXGI_FORMAT {
+        match self {
+            Self::Srgb => DXGI_FORMAT_R8G8B8A8_UNORM_SRGB,
+            _ => DXGI_FORMAT_R8G8B8A8_UNORM,
+        }
+    }
+}
+
+/// Checks if the hardware does sRGB encoding when writing into `format`.
+pub fn is_srgb_format(format: DXGI_FORMAT) -> bool {
+    matches!(
+        format,
+        DXGI_FORMAT_R8G8B8A8_UNORM_SRGB
+            | DXGI_FORMAT_B8G8R8A8_UNORM_SRGB
+            | DXGI_FORMAT_B8G8R8X8_UNORM_SRGB
+    )
+}
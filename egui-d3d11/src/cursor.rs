@@ -0,0 +1,153 @@
+//! Mapping of egui's cursor icons onto system cursors.
+use egui::CursorIcon;
+use windows::{
+    core::PCWSTR,
+    Win32::UI::WindowsAndMessaging::{LoadCursorW, SetCursor, HCURSOR},
+};
+
+pub const WM_SETCURSOR: u32 = 0x0020;
+/// Hit test result for the client area of the window.
+pub const HTCLIENT: isize = 1;
+
+pub const IDC_ARROW: u16 = 32512;
+pub const IDC_IBEAM: u16 = 32513;
+pub const IDC_WAIT: u16 = 32514;
+pub const IDC_CROSS: u16 = 32515;
+pub const IDC_SIZENWSE: u16 = 32642;
+pub const IDC_SIZENESW: u16 = 32643;
+pub const IDC_SIZEWE: u16 = 32644;
+pub const IDC_SIZENS: u16 = 32645;
+pub const IDC_SIZEALL: u16 = 32646;
+pub const IDC_NO: u16 = 32648;
+pub const IDC_HAND: u16 = 32649;
+pub const IDC_APPSTARTING: u16 = 32650;
+pub const IDC_HELP: u16 = 32651;
+
+/// Returns id of the system cursor resource for `icon`.
+/// `None` means that the cursor should be hidden.
+pub fn cursor_resource(icon: CursorIcon) -> Option<u16> {
+    let id = match icon {
+        CursorIcon::None => return None,
+        CursorIcon::Default => IDC_ARROW,
+        CursorIcon::ContextMenu => IDC_ARROW,
+        CursorIcon::Help => IDC_HELP,
+        CursorIcon::PointingHand => IDC_HAND,
+        CursorIcon::Progress => IDC_APPSTARTING,
+        CursorIcon::Wait => IDC_WAIT,
+        CursorIcon::Cell => IDC_CROSS,
+        CursorIcon::Crosshair => IDC_CROSS,
+        CursorIcon::Text => IDC_IBEAM,
+        CursorIcon::VerticalText => IDC_IBEAM,
+        CursorIcon::Alias => IDC_ARROW,
+        CursorIcon::Copy => IDC_ARROW,
+        CursorIcon::Move => IDC_SIZEALL,
+        CursorIcon::NoDrop => IDC_NO,
+        CursorIcon::NotAllowed => IDC_NO,
+        CursorIcon::Grab => IDC_SIZEALL,
+        CursorIcon::Grabbing => IDC_SIZEALL,
+        CursorIcon::AllScroll => IDC_SIZEALL,
+        CursorIcon::ResizeHorizontal => IDC_SIZEWE,
+        CursorIcon::ResizeNeSw => IDC_SIZENESW,
+        CursorIcon::ResizeNwSe => IDC_SIZENWSE,
+        CursorIcon::ResizeVertical => IDC_SIZENS,
+        CursorIcon::ZoomIn => IDC_ARROW,
+        CursorIcon::ZoomOut => IDC_ARROW,
+    };
+
+    Some(id)
+}
+
+/// Checks if `WM_SETCURSOR` was sent for the client area of the window.
+#[inline]
+pub fn is_client_area(lparam: isize) -> bool {
+    lparam & 0xFFFF == HTCLIENT
+}
+
+/// Makes `icon` the current cursor. Must be called from the window's thread.
+pub fn set_cursor(icon: CursorIcon) {
+    unsafe {
+        let cursor = match cursor_resource(icon) {
+            Some(id) => LoadCursorW(None, PCWSTR(id as usize as _)).unwrap_or_default(),
+            None => HCURSOR::default(),
+        };
+
+        SetCursor(cursor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SYSTEM_CURSORS: &[u16] = &[
+        IDC_ARROW,
+        IDC_IBEAM,
+        IDC_WAIT,
+        IDC_CROSS,
+        IDC_SIZENWSE,
+        IDC_SIZENESW,
+        IDC_SIZEWE,
+        IDC_SIZENS,
+        IDC_SIZEALL,
+        IDC_NO,
+        IDC_HAND,
+        IDC_APPSTARTING,
+        IDC_HELP,
+    ];
+
+    #[test]
+    fn none_hides_the_cursor() {
+        assert_eq!(cursor_resource(CursorIcon::None), None);
+    }
+
+    #[test]
+    fn every_icon_maps_to_a_system_cursor() {
+        for icon in [
+            CursorIcon::Default,
+            CursorIcon::ContextMenu,
+            CursorIcon::Help,
+            CursorIcon::PointingHand,
+            CursorIcon::Progress,
+            CursorIcon::Wait,
+            CursorIcon::Cell,
+            CursorIcon::Crosshair,
+            CursorIcon::Text,
+            CursorIcon::VerticalText,
+            CursorIcon::Alias,
+            CursorIcon::Copy,
+            CursorIcon::Move,
+            CursorIcon::NoDrop,
+            CursorIcon::NotAllowed,
+            CursorIcon::Grab,
+            CursorIcon::Grabbing,
+            CursorIcon::AllScroll,
+            CursorIcon::ResizeHorizontal,
+            CursorIcon::ResizeNeSw,
+            CursorIcon::ResizeNwSe,
+            CursorIcon::ResizeVertical,
+            CursorIcon::ZoomIn,
+            CursorIcon::ZoomOut,
+        ] {
+            let id = cursor_resource(icon);
+            assert!(
+                matches!(id, Some(id) if SYSTEM_CURSORS.contains(&id)),
+                "{:?} maps to {:?}",
+                icon,
+                id
+            );
+        }
+
+        assert_eq!(cursor_resource(CursorIcon::Default), Some(IDC_ARROW));
+        assert_eq!(cursor_resource(CursorIcon::Text), Some(IDC_IBEAM));
+        assert_eq!(cursor_resource(CursorIcon::PointingHand), Some(IDC_HAND));
+    }
+
+    #[test]
+    fn only_client_area_is_recognized() {
+        assert!(is_client_area(HTCLIENT));
+        // High word of `lparam` is the mouse message that triggered `WM_SETCURSOR`.
+        assert!(is_client_area(0x0200_0000 | HTCLIENT));
+        assert!(!is_client_area(0));
+        assert!(!is_client_area(2));
+    }
+}
@@ -0,0 +1,739 @@
+//! Translation of window messages into egui events.
+//! Uses plain numeric constants and never calls into Win32,
+//! so everything here builds and behaves the same on every target.
+use crate::{input::InputResult, keymap::translate_key};
+use egui::{Event, Key, Modifiers, PointerButton, Pos2, Vec2};
+
+pub const WM_SIZE: u32 = 0x0005;
+pub const WM_ACTIVATE: u32 = 0x0006;
+pub const WM_SETFOCUS: u32 = 0x0007;
+pub const WM_KILLFOCUS: u32 = 0x0008;
+pub const WM_KEYDOWN: u32 = 0x0100;
+pub const WM_KEYUP: u32 = 0x0101;
+pub const WM_CHAR: u32 = 0x0102;
+pub const WM_SYSKEYDOWN: u32 = 0x0104;
+pub const WM_SYSKEYUP: u32 = 0x0105;
+pub const WM_IME_STARTCOMPOSITION: u32 = 0x010D;
+pub const WM_IME_ENDCOMPOSITION: u32 = 0x010E;
+pub const WM_IME_COMPOSITION: u32 = 0x010F;
+pub const WM_MOUSEMOVE: u32 = 0x0200;
+pub const WM_LBUTTONDOWN: u32 = 0x0201;
+pub const WM_LBUTTONUP: u32 = 0x0202;
+pub const WM_LBUTTONDBLCLK: u32 = 0x0203;
+pub const WM_RBUTTONDOWN: u32 = 0x0204;
+pub const WM_RBUTTONUP: u32 = 0x0205;
+pub const WM_RBUTTONDBLCLK: u32 = 0x0206;
+pub const WM_MBUTTONDOWN: u32 = 0x0207;
+pub const WM_MBUTTONUP: u32 = 0x0208;
+pub const WM_MBUTTONDBLCLK: u32 = 0x0209;
+pub const WM_MOUSEWHEEL: u32 = 0x020A;
+pub const WM_XBUTTONDOWN: u32 = 0x020B;
+pub const WM_XBUTTONUP: u32 = 0x020C;
+pub const WM_XBUTTONDBLCLK: u32 = 0x020D;
+pub const WM_MOUSEHWHEEL: u32 = 0x020E;
+pub const WM_MOUSELEAVE: u32 = 0x02A3;
+pub const WM_DPICHANGED: u32 = 0x02E0;
+
+pub const MK_CONTROL: usize = 0x0008;
+
+pub const GCS_COMPSTR: u32 = 0x0008;
+pub const GCS_RESULTSTR: u32 = 0x0800;
+
+const WA_INACTIVE: usize = 0;
+const XBUTTON1: usize = 0x0001;
+const XBUTTON2: usize = 0x0002;
+
+const WHEEL_DELTA: f32 = 120.;
+const USER_DEFAULT_SCREEN_DPI: f32 = 96.;
+
+/// State of the keyboard at the moment message arrived.
+/// Everything that can't be derived from the message itself goes here.
+#[derive(Debug, Clone, Default)]
+pub struct KeyboardSnapshot {
+    /// Modifiers reported with keyboard and mouse events.
+    pub modifiers: Modifiers,
+    /// Keys that are currently held, they get released when window loses focus.
+    pub held_keys: Vec<Key>,
+}
+
+/// Clipboard shortcut recognized on a key press.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardAction {
+    Copy,
+    Cut,
+    Paste,
+}
+
+/// Checks if pressing `key` with `modifiers` is one of the clipboard shortcuts.
+/// Besides `Ctrl+C`, `Ctrl+X` and `Ctrl+V`, `Ctrl+Insert` copies and `Shift+Insert` pastes.
+pub fn clipboard_action(key: Key, modifiers: Modifiers) -> Option<ClipboardAction> {
+    match key {
+        Key::C | Key::Insert if modifiers.ctrl && !modifiers.shift => Some(ClipboardAction::Copy),
+        Key::X if modifiers.ctrl => Some(ClipboardAction::Cut),
+        Key::V if modifiers.ctrl => Some(ClipboardAction::Paste),
+        Key::Insert if modifiers.shift && !modifiers.ctrl => Some(ClipboardAction::Paste),
+        _ => None,
+    }
+}
+
+/// Strings carried by a `WM_IME_COMPOSITION` message.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Composition {
+    /// Text that is still being composed.
+    pub composition: Option<String>,
+    /// Text the user has committed.
+    pub result: Option<String>,
+}
+
+/// Joins UTF-16 code units coming with consecutive `WM_CHAR` messages.
+/// Characters outside of the BMP arrive as two messages, one per surrogate.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Utf16Decoder {
+    high_surrogate: Option<u16>,
+}
+
+impl Utf16Decoder {
+    /// Feeds a single code unit, returns a character once it's complete.
+    /// Lone surrogates are dropped.
+    pub fn push(&mut self, unit: u16) -> Option<char> {
+        match unit {
+            0xD800..=0xDBFF => {
+                self.high_surrogate = Some(unit);
+                None
+            }
+            0xDC00..=0xDFFF => {
+                let high = self.high_surrogate.take()?;
+                char::decode_utf16([high, unit]).next()?.ok()
+            }
+            _ => {
+                self.high_surrogate = None;
+                char::from_u32(unit as _)
+            }
+        }
+    }
+}
+
+/// Translates `WM_CHAR` into egui's text event.
+pub fn decode_char(wparam: usize, decoder: &mut Utf16Decoder) -> Option<Event> {
+    decoder
+        .push(wparam as u16)
+        .filter(|ch| !ch.is_control())
+        .map(|ch| Event::Text(ch.into()))
+}
+
+/// Releases of every key in `held_keys`, for when their real releases won't reach egui.
+pub fn key_releases(held_keys: &[Key]) -> Vec<Event> {
+    held_keys
+        .iter()
+        .map(|&key| Event::Key {
+            key,
+            pressed: false,
+            modifiers: Modifiers::default(),
+        })
+        .collect()
+}
+
+/// Translates window messages into egui events, carrying surrogates between `WM_CHAR` messages.
+/// Other messages don't touch the pending surrogate, so key events in between don't break pairs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MessageDecoder {
+    utf16: Utf16Decoder,
+}
+
+impl MessageDecoder {
+    pub fn decode(
+        &mut self,
+        msg: u32,
+        wparam: usize,
+        lparam: isize,
+        keyboard: &KeyboardSnapshot,
+    ) -> Vec<Event> {
+        match msg {
+            WM_CHAR => decode_char(wparam, &mut self.utf16).into_iter().collect(),
+            _ => decode_message(msg, wparam, lparam, keyboard),
+        }
+    }
+}
+
+/// Translates a single window message into egui events.
+/// Unknown messages produce no events, `WM_CHAR` is handled by [`MessageDecoder`].
+pub fn decode_message(
+    msg: u32,
+    wparam: usize,
+    lparam: isize,
+    keyboard: &KeyboardSnapshot,
+) -> Vec<Event> {
+    match msg {
+        WM_MOUSEMOVE => vec![Event::PointerMoved(get_pos(lparam))],
+        WM_LBUTTONDOWN | WM_LBUTTONDBLCLK => {
+            vec![button(lparam, keyboard, PointerButton::Primary, true)]
+        }
+        WM_LBUTTONUP => vec![button(lparam, keyboard, PointerButton::Primary, false)],
+        WM_RBUTTONDOWN | WM_RBUTTONDBLCLK => {
+            vec![button(lparam, keyboard, PointerButton::Secondary, true)]
+        }
+        WM_RBUTTONUP => vec![button(lparam, keyboard, PointerButton::Secondary, false)],
+        WM_MBUTTONDOWN | WM_MBUTTONDBLCLK => {
+            vec![button(lparam, keyboard, PointerButton::Middle, true)]
+        }
+        WM_MBUTTONUP => vec![button(lparam, keyboard, PointerButton::Middle, false)],
+        WM_XBUTTONDOWN | WM_XBUTTONDBLCLK => get_xbutton(wparam)
+            .map(|b| button(lparam, keyboard, b, true))
+            .into_iter()
+            .collect(),
+        WM_XBUTTONUP => get_xbutton(wparam)
+            .map(|b| button(lparam, keyboard, b, false))
+            .into_iter()
+            .collect(),
+        WM_MOUSELEAVE => vec![Event::PointerGone],
+        WM_KILLFOCUS | WM_ACTIVATE if is_focus_lost(msg, wparam) => {
+            key_releases(&keyboard.held_keys)
+        }
+        WM_IME_STARTCOMPOSITION => vec![Event::CompositionStart],
+        // Committed text comes with `WM_IME_COMPOSITION`, ending without it is a cancellation.
+        WM_IME_ENDCOMPOSITION => vec![Event::CompositionEnd(String::new())],
+        WM_MOUSEWHEEL => vec![wheel(wparam, |delta| Vec2::new(0., delta))],
+        WM_MOUSEHWHEEL => vec![wheel(wparam, |delta| Vec2::new(delta, 0.))],
+        WM_KEYDOWN | WM_SYSKEYDOWN => {
+            let modifiers = keyboard.modifiers;
+            let mut events = vec![];
+
+            if let Some(key) = translate_key(wparam, lparam) {
+                match clipboard_action(key, modifiers) {
+                    Some(ClipboardAction::Copy) => events.push(Event::Copy),
+                    Some(ClipboardAction::Cut) => events.push(Event::Cut),
+                    // Reading clipboard is up to the caller.
+                    Some(ClipboardAction::Paste) | None => {}
+                }
+
+                events.push(Event::Key {
+                    pressed: true,
+                    modifiers,
+                    key,
+                });
+            }
+            events
+        }
+        WM_KEYUP | WM_SYSKEYUP => translate_key(wparam, lparam)
+            .map(|key| Event::Key {
+                pressed: false,
+                modifiers: keyboard.modifiers,
+                key,
+            })
+            .into_iter()
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Translates strings of `WM_IME_COMPOSITION` into egui events.
+/// egui ignores updates after the composition has ended, so a new one is started
+/// when the message commits text and keeps composing at the same time.
+pub fn decode_composition(composition: &Composition) -> Vec<Event> {
+    let mut events = vec![];
+
+    if let Some(result) = &composition.result {
+        events.push(Event::CompositionEnd(result.clone()));
+    }
+
+    match &composition.composition {
+        Some(text) if composition.result.is_some() && !text.is_empty() => {
+            events.push(Event::CompositionStart);
+            events.push(Event::CompositionUpdate(text.clone()));
+        }
+        Some(text) if composition.result.is_none() => {
+            events.push(Event::CompositionUpdate(text.clone()));
+        }
+        _ => {}
+    }
+
+    events
+}
+
+/// High-level kind of the message, see [`InputResult`].
+pub fn classify_message(msg: u32, wparam: usize) -> InputResult {
+    match msg {
+        WM_MOUSEMOVE => InputResult::MouseMove,
+        WM_LBUTTONDOWN | WM_LBUTTONDBLCLK | WM_LBUTTONUP => InputResult::MouseLeft,
+        WM_RBUTTONDOWN | WM_RBUTTONDBLCLK | WM_RBUTTONUP => InputResult::MouseRight,
+        WM_MBUTTONDOWN | WM_MBUTTONDBLCLK | WM_MBUTTONUP => InputResult::MouseMiddle,
+        WM_CHAR => InputResult::Character,
+        WM_MOUSEWHEEL | WM_MOUSEHWHEEL if wparam & MK_CONTROL != 0 => InputResult::Zoom,
+        WM_MOUSEWHEEL | WM_MOUSEHWHEEL => InputResult::Scroll,
+        WM_KEYDOWN | WM_SYSKEYDOWN | WM_KEYUP | WM_SYSKEYUP => InputResult::Key,
+        WM_XBUTTONDOWN | WM_XBUTTONDBLCLK | WM_XBUTTONUP => InputResult::MouseExtra,
+        WM_MOUSELEAVE => InputResult::MouseLeave,
+        WM_SETFOCUS | WM_KILLFOCUS | WM_ACTIVATE => InputResult::Focus,
+        WM_IME_STARTCOMPOSITION | WM_IME_COMPOSITION | WM_IME_ENDCOMPOSITION => {
+            InputResult::Composition
+        }
+        _ => InputResult::Unknown,
+    }
+}
+
+/// Checks if the message releases a key or a mouse button.
+pub fn is_release(msg: u32) -> bool {
+    matches!(
+        msg,
+        WM_KEYUP | WM_SYSKEYUP | WM_LBUTTONUP | WM_RBUTTONUP | WM_MBUTTONUP | WM_XBUTTONUP
+    )
+}
+
+/// Checks if the message tells that window has lost keyboard focus.
+pub fn is_focus_lost(msg: u32, wparam: usize) -> bool {
+    match msg {
+        WM_KILLFOCUS => true,
+        WM_ACTIVATE => wparam & 0xFFFF == WA_INACTIVE,
+        _ => false,
+    }
+}
+
+/// Applies `map` to positions of pointer events, other events are returned as is.
+pub fn map_pointer_pos(event: Event, map: impl FnOnce(Pos2) -> Pos2) -> Event {
+    match event {
+        Event::PointerMoved(pos) => Event::PointerMoved(map(pos)),
+        Event::PointerButton {
+            pos,
+            button,
+            pressed,
+            modifiers,
+        } => Event::PointerButton {
+            pos: map(pos),
+            button,
+            pressed,
+            modifiers,
+        },
+        event => event,
+    }
+}
+
+/// Converts window's DPI into egui's pixels per point, 96 DPI is a scale of `1.0`.
+/// `WM_DPICHANGED` carries the new DPI in the low word of `wparam`.
+#[inline]
+pub fn dpi_to_pixels_per_point(dpi: u32) -> f32 {
+    if dpi == 0 {
+        1.
+    } else {
+        dpi as f32 / USER_DEFAULT_SCREEN_DPI
+    }
+}
+
+/// Extracts new client size from `lparam` of `WM_SIZE`.
+pub fn get_size(lparam: isize) -> Vec2 {
+    Vec2::new((lparam & 0xFFFF) as f32, (lparam >> 16 & 0xFFFF) as f32)
+}
+
+/// Extracts signed client coordinates packed into `lparam`.
+pub fn get_pos(lparam: isize) -> Pos2 {
+    let x = (lparam & 0xFFFF) as i16 as f32;
+    let y = (lparam >> 16 & 0xFFFF) as i16 as f32;
+
+    Pos2::new(x, y)
+}
+
+fn button(
+    lparam: isize,
+    keyboard: &KeyboardSnapshot,
+    button: PointerButton,
+    pressed: bool,
+) -> Event {
+    Event::PointerButton {
+        pos: get_pos(lparam),
+        button,
+        pressed,
+        modifiers: keyboard.modifiers,
+    }
+}
+
+fn get_xbutton(wparam: usize) -> Option<PointerButton> {
+    match (wparam >> 16) & 0xFFFF {
+        XBUTTON1 => Some(PointerButton::Extra1),
+        XBUTTON2 => Some(PointerButton::Extra2),
+        _ => None,
+    }
+}
+
+fn wheel(wparam: usize, axis: impl FnOnce(f32) -> Vec2) -> Event {
+    let delta = (wparam >> 16) as i16 as f32 * 10. / WHEEL_DELTA;
+
+    if wparam & MK_CONTROL != 0 {
+        Event::Zoom(if delta > 0. { 1.5 } else { 0.5 })
+    } else {
+        Event::Scroll(axis(delta))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::VK_F1;
+
+    const NONE: Modifiers = Modifiers::NONE;
+    const CTRL: Modifiers = Modifiers {
+        alt: false,
+        ctrl: true,
+        shift: false,
+        mac_cmd: false,
+        command: true,
+    };
+
+    /// Packs client coordinates the same way Windows does for mouse messages.
+    fn pos(x: i16, y: i16) -> isize {
+        ((y as u16 as isize) << 16) | x as u16 as isize
+    }
+
+    /// Packs wheel delta and held mouse keys into `wparam`.
+    fn wheel_delta(delta: i16, keys: usize) -> usize {
+        ((delta as u16 as usize) << 16) | keys
+    }
+
+    fn snapshot(modifiers: Modifiers, held_keys: &[Key]) -> KeyboardSnapshot {
+        KeyboardSnapshot {
+            modifiers,
+            held_keys: held_keys.to_vec(),
+        }
+    }
+
+    fn pointer(
+        x: f32,
+        y: f32,
+        button: PointerButton,
+        pressed: bool,
+        modifiers: Modifiers,
+    ) -> Event {
+        Event::PointerButton {
+            pos: Pos2::new(x, y),
+            button,
+            pressed,
+            modifiers,
+        }
+    }
+
+    fn key(key: Key, pressed: bool, modifiers: Modifiers) -> Event {
+        Event::Key {
+            key,
+            pressed,
+            modifiers,
+        }
+    }
+
+    #[test]
+    fn decodes_every_handled_message() {
+        use PointerButton::*;
+
+        let idle = snapshot(NONE, &[]);
+        let ctrl = snapshot(CTRL, &[]);
+        let holding = snapshot(CTRL, &[Key::A, Key::Space]);
+        let released = vec![key(Key::A, false, NONE), key(Key::Space, false, NONE)];
+        let xbutton1 = XBUTTON1 << 16;
+        let xbutton2 = XBUTTON2 << 16;
+
+        #[rustfmt::skip]
+        let cases: Vec<(u32, usize, isize, &KeyboardSnapshot, Vec<Event>)> = vec![
+            (WM_MOUSEMOVE, 0, pos(10, 20), &idle, vec![Event::PointerMoved(Pos2::new(10., 20.))]),
+            (WM_MOUSEMOVE, 0, pos(-5, -300), &idle, vec![Event::PointerMoved(Pos2::new(-5., -300.))]),
+            (WM_MOUSEMOVE, 0, pos(i16::MIN, i16::MAX), &idle, vec![Event::PointerMoved(Pos2::new(-32768., 32767.))]),
+            (WM_LBUTTONDOWN, 0, pos(-1, 7), &ctrl, vec![pointer(-1., 7., Primary, true, CTRL)]),
+            (WM_LBUTTONDBLCLK, 0, pos(1, 2), &idle, vec![pointer(1., 2., Primary, true, NONE)]),
+            (WM_LBUTTONUP, 0, pos(1, -2), &idle, vec![pointer(1., -2., Primary, false, NONE)]),
+            (WM_RBUTTONDOWN, 0, pos(3, 4), &idle, vec![pointer(3., 4., Secondary, true, NONE)]),
+            (WM_RBUTTONDBLCLK, 0, pos(3, 4), &idle, vec![pointer(3., 4., Secondary, true, NONE)]),
+            (WM_RBUTTONUP, 0, pos(-3, -4), &ctrl, vec![pointer(-3., -4., Secondary, false, CTRL)]),
+            (WM_MBUTTONDOWN, 0, pos(5, 6), &idle, vec![pointer(5., 6., Middle, true, NONE)]),
+            (WM_MBUTTONDBLCLK, 0, pos(5, 6), &idle, vec![pointer(5., 6., Middle, true, NONE)]),
+            (WM_MBUTTONUP, 0, pos(5, 6), &idle, vec![pointer(5., 6., Middle, false, NONE)]),
+            (WM_XBUTTONDOWN, xbutton1, pos(7, 8), &idle, vec![pointer(7., 8., Extra1, true, NONE)]),
+            (WM_XBUTTONDBLCLK, xbutton2, pos(7, 8), &idle, vec![pointer(7., 8., Extra2, true, NONE)]),
+            (WM_XBUTTONUP, xbutton2, pos(-7, 8), &idle, vec![pointer(-7., 8., Extra2, false, NONE)]),
+            (WM_XBUTTONDOWN, 3 << 16, pos(7, 8), &idle, vec![]),
+            (WM_MOUSELEAVE, 0, 0, &idle, vec![Event::PointerGone]),
+            (WM_MOUSEWHEEL, wheel_delta(120, 0), pos(-10, -10), &idle, vec![Event::Scroll(Vec2::new(0., 10.))]),
+            (WM_MOUSEWHEEL, wheel_delta(-120, 0), 0, &idle, vec![Event::Scroll(Vec2::new(0., -10.))]),
+            (WM_MOUSEWHEEL, wheel_delta(-240, 0), 0, &idle, vec![Event::Scroll(Vec2::new(0., -20.))]),
+            (WM_MOUSEWHEEL, wheel_delta(60, 0), 0, &idle, vec![Event::Scroll(Vec2::new(0., 5.))]),
+            (WM_MOUSEHWHEEL, wheel_delta(120, 0), 0, &idle, vec![Event::Scroll(Vec2::new(10., 0.))]),
+            (WM_MOUSEHWHEEL, wheel_delta(-120, 0), 0, &idle, vec![Event::Scroll(Vec2::new(-10., 0.))]),
+            (WM_MOUSEWHEEL, wheel_delta(120, MK_CONTROL), 0, &ctrl, vec![Event::Zoom(1.5)]),
+            (WM_MOUSEWHEEL, wheel_delta(-120, MK_CONTROL), 0, &ctrl, vec![Event::Zoom(0.5)]),
+            (WM_MOUSEHWHEEL, wheel_delta(-120, MK_CONTROL), 0, &ctrl, vec![Event::Zoom(0.5)]),
+            (WM_KEYDOWN, 0x41, 0, &idle, vec![key(Key::A, true, NONE)]),
+            (WM_KEYDOWN, 0x43, 0, &ctrl, vec![Event::Copy, key(Key::C, true, CTRL)]),
+            (WM_KEYDOWN, 0x58, 0, &ctrl, vec![Event::Cut, key(Key::X, true, CTRL)]),
+            (WM_KEYDOWN, 0x56, 0, &ctrl, vec![key(Key::V, true, CTRL)]),
+            (WM_KEYDOWN, 0xFF, 0, &idle, vec![]),
+            (WM_SYSKEYDOWN, VK_F1 as _, 0, &idle, vec![key(Key::F1, true, NONE)]),
+            (WM_KEYUP, 0x41, 0, &ctrl, vec![key(Key::A, false, CTRL)]),
+            (WM_SYSKEYUP, VK_F1 as _, 0, &idle, vec![key(Key::F1, false, NONE)]),
+            (WM_KILLFOCUS, 0, 0, &holding, released.clone()),
+            (WM_KILLFOCUS, 0, 0, &idle, vec![]),
+            (WM_ACTIVATE, WA_INACTIVE, 0, &holding, released),
+            (WM_ACTIVATE, 1, 0, &holding, vec![]),
+            (WM_SETFOCUS, 0, 0, &holding, vec![]),
+            (WM_IME_STARTCOMPOSITION, 0, 0, &idle, vec![Event::CompositionStart]),
+            (WM_IME_ENDCOMPOSITION, 0, 0, &idle, vec![Event::CompositionEnd(String::new())]),
+            (WM_CHAR, 'a' as _, 0, &idle, vec![]),
+            (WM_SIZE, 0, pos(800, 600), &idle, vec![]),
+            (WM_DPICHANGED, 144, 0, &idle, vec![]),
+        ];
+
+        for (msg, wparam, lparam, keyboard, expected) in cases {
+            assert_eq!(
+                decode_message(msg, wparam, lparam, keyboard),
+                expected,
+                "msg {:#x}, wparam {:#x}, lparam {:#x}",
+                msg,
+                wparam,
+                lparam
+            );
+        }
+    }
+
+    #[test]
+    fn classifies_every_handled_message() {
+        #[rustfmt::skip]
+        let cases = [
+            (WM_MOUSEMOVE, 0, InputResult::MouseMove),
+            (WM_LBUTTONDOWN, 0, InputResult::MouseLeft),
+            (WM_LBUTTONDBLCLK, 0, InputResult::MouseLeft),
+            (WM_LBUTTONUP, 0, InputResult::MouseLeft),
+            (WM_RBUTTONDOWN, 0, InputResult::MouseRight),
+            (WM_RBUTTONDBLCLK, 0, InputResult::MouseRight),
+            (WM_RBUTTONUP, 0, InputResult::MouseRight),
+            (WM_MBUTTONDOWN, 0, InputResult::MouseMiddle),
+            (WM_MBUTTONDBLCLK, 0, InputResult::MouseMiddle),
+            (WM_MBUTTONUP, 0, InputResult::MouseMiddle),
+            (WM_XBUTTONDOWN, 0, InputResult::MouseExtra),
+            (WM_XBUTTONDBLCLK, 0, InputResult::MouseExtra),
+            (WM_XBUTTONUP, 0, InputResult::MouseExtra),
+            (WM_CHAR, 0, InputResult::Character),
+            (WM_MOUSEWHEEL, wheel_delta(-120, 0), InputResult::Scroll),
+            (WM_MOUSEHWHEEL, wheel_delta(120, 0), InputResult::Scroll),
+            (WM_MOUSEWHEEL, wheel_delta(120, MK_CONTROL), InputResult::Zoom),
+            (WM_MOUSEHWHEEL, wheel_delta(-120, MK_CONTROL), InputResult::Zoom),
+            (WM_KEYDOWN, 0, InputResult::Key),
+            (WM_SYSKEYDOWN, 0, InputResult::Key),
+            (WM_KEYUP, 0, InputResult::Key),
+            (WM_SYSKEYUP, 0, InputResult::Key),
+            (WM_MOUSELEAVE, 0, InputResult::MouseLeave),
+            (WM_SETFOCUS, 0, InputResult::Focus),
+            (WM_KILLFOCUS, 0, InputResult::Focus),
+            (WM_ACTIVATE, WA_INACTIVE, InputResult::Focus),
+            (WM_IME_STARTCOMPOSITION, 0, InputResult::Composition),
+            (WM_IME_COMPOSITION, 0, InputResult::Composition),
+            (WM_IME_ENDCOMPOSITION, 0, InputResult::Composition),
+            (WM_SIZE, 0, InputResult::Unknown),
+            (WM_DPICHANGED, 0, InputResult::Unknown),
+        ];
+
+        for (msg, wparam, expected) in cases {
+            assert_eq!(classify_message(msg, wparam), expected, "msg {:#x}", msg);
+        }
+    }
+
+    #[test]
+    fn unpacks_signed_positions_and_sizes() {
+        assert_eq!(get_pos(pos(0, 0)), Pos2::new(0., 0.));
+        assert_eq!(get_pos(pos(-1, -1)), Pos2::new(-1., -1.));
+        assert_eq!(get_pos(pos(-1920, 540)), Pos2::new(-1920., 540.));
+        assert_eq!(get_pos(pos(300, -20)), Pos2::new(300., -20.));
+        assert_eq!(get_size(pos(1920, 1080)), Vec2::new(1920., 1080.));
+    }
+
+    #[test]
+    fn only_focus_loss_is_reported_as_lost() {
+        assert!(is_focus_lost(WM_KILLFOCUS, 0));
+        assert!(is_focus_lost(WM_ACTIVATE, WA_INACTIVE));
+        // High word of `WM_ACTIVATE` tells if the window is minimized.
+        assert!(is_focus_lost(WM_ACTIVATE, 0x1_0000));
+        assert!(!is_focus_lost(WM_ACTIVATE, 1));
+        assert!(!is_focus_lost(WM_ACTIVATE, 2));
+        assert!(!is_focus_lost(WM_SETFOCUS, 0));
+    }
+
+    fn composition(composition: Option<&str>, result: Option<&str>) -> Composition {
+        Composition {
+            composition: composition.map(Into::into),
+            result: result.map(Into::into),
+        }
+    }
+
+    #[test]
+    fn composition_update_only() {
+        assert_eq!(
+            decode_composition(&composition(Some("にほ"), None)),
+            [Event::CompositionUpdate("にほ".into())]
+        );
+    }
+
+    #[test]
+    fn composition_result_only() {
+        assert_eq!(
+            decode_composition(&composition(None, Some("日本"))),
+            [Event::CompositionEnd("日本".into())]
+        );
+    }
+
+    #[test]
+    fn composition_result_starts_new_composition() {
+        assert_eq!(
+            decode_composition(&composition(Some("ご"), Some("日本"))),
+            [
+                Event::CompositionEnd("日本".into()),
+                Event::CompositionStart,
+                Event::CompositionUpdate("ご".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn composition_empty_string() {
+        // Composition got cleared, e.g. by deleting every character.
+        assert_eq!(
+            decode_composition(&composition(Some(""), None)),
+            [Event::CompositionUpdate("".into())]
+        );
+        // Nothing left to compose after the commit, no need to start again.
+        assert_eq!(
+            decode_composition(&composition(Some(""), Some("日本"))),
+            [Event::CompositionEnd("日本".into())]
+        );
+        assert!(decode_composition(&Composition::default()).is_empty());
+    }
+
+    fn text(text: &str) -> Option<Event> {
+        Some(Event::Text(text.into()))
+    }
+
+    #[test]
+    fn joins_surrogate_pairs() {
+        let mut decoder = Utf16Decoder::default();
+
+        // U+1F600, grinning face.
+        assert_eq!(decode_char(0xD83D, &mut decoder), None);
+        assert_eq!(decode_char(0xDE00, &mut decoder), text("😀"));
+        // U+10000, first character outside of the BMP.
+        assert_eq!(decode_char(0xD800, &mut decoder), None);
+        assert_eq!(decode_char(0xDC00, &mut decoder), text("\u{10000}"));
+        // U+10FFFF, last one.
+        assert_eq!(decode_char(0xDBFF, &mut decoder), None);
+        assert_eq!(decode_char(0xDFFF, &mut decoder), text("\u{10FFFF}"));
+
+        assert_eq!(decode_char('a' as _, &mut decoder), text("a"));
+        assert_eq!(decode_char('ж' as _, &mut decoder), text("ж"));
+        assert_eq!(decode_char(0x08, &mut decoder), None);
+    }
+
+    #[test]
+    fn drops_lone_surrogates() {
+        let mut decoder = Utf16Decoder::default();
+
+        // Low surrogate without high one.
+        assert_eq!(decode_char(0xDE00, &mut decoder), None);
+        assert_eq!(decode_char('a' as _, &mut decoder), text("a"));
+
+        // High surrogate followed by something else than low one.
+        assert_eq!(decode_char(0xD83D, &mut decoder), None);
+        assert_eq!(decode_char('b' as _, &mut decoder), text("b"));
+        assert_eq!(decode_char(0xDE00, &mut decoder), None);
+
+        // Two high surrogates in a row, the second one starts the pair.
+        assert_eq!(decode_char(0xD800, &mut decoder), None);
+        assert_eq!(decode_char(0xD83D, &mut decoder), None);
+        assert_eq!(decode_char(0xDE00, &mut decoder), text("😀"));
+        assert_eq!(decoder, Utf16Decoder::default());
+    }
+
+    #[test]
+    fn key_events_between_surrogates_keep_the_pair() {
+        let mut decoder = MessageDecoder::default();
+        let idle = snapshot(NONE, &[]);
+
+        assert_eq!(decoder.decode(WM_CHAR, 0xD83D, 0, &idle), []);
+        assert_eq!(
+            decoder.decode(WM_KEYDOWN, 0x41, 0, &idle),
+            [key(Key::A, true, NONE)]
+        );
+        assert_eq!(
+            decoder.decode(WM_KEYUP, 0x41, 0, &idle),
+            [key(Key::A, false, NONE)]
+        );
+        assert_eq!(decoder.decode(WM_MOUSEMOVE, 0, 0, &idle).len(), 1);
+        assert_eq!(
+            decoder.decode(WM_CHAR, 0xDE00, 0, &idle),
+            [Event::Text("😀".into())]
+        );
+
+        // Anything else than a low surrogate coming with `WM_CHAR` still breaks the pair.
+        assert_eq!(decoder.decode(WM_CHAR, 0xD83D, 0, &idle), []);
+        assert_eq!(
+            decoder.decode(WM_CHAR, 'a' as _, 0, &idle),
+            [Event::Text("a".into())]
+        );
+        assert_eq!(decoder.decode(WM_CHAR, 0xDE00, 0, &idle), []);
+        assert_eq!(decoder, MessageDecoder::default());
+    }
+
+    #[test]
+    fn recognizes_clipboard_shortcuts() {
+        let shift = Modifiers::SHIFT;
+        let ctrl_shift = Modifiers {
+            shift: true,
+            ..CTRL
+        };
+        let alt = Modifiers::ALT;
+
+        #[rustfmt::skip]
+        let cases = [
+            (Key::C, CTRL, Some(ClipboardAction::Copy)),
+            (Key::X, CTRL, Some(ClipboardAction::Cut)),
+            (Key::V, CTRL, Some(ClipboardAction::Paste)),
+            (Key::Insert, CTRL, Some(ClipboardAction::Copy)),
+            (Key::Insert, shift, Some(ClipboardAction::Paste)),
+            (Key::Insert, ctrl_shift, None),
+            (Key::Insert, NONE, None),
+            (Key::Insert, alt, None),
+            (Key::C, ctrl_shift, None),
+            (Key::X, ctrl_shift, Some(ClipboardAction::Cut)),
+            (Key::V, ctrl_shift, Some(ClipboardAction::Paste)),
+            (Key::C, NONE, None),
+            (Key::V, shift, None),
+            (Key::Delete, shift, None),
+            (Key::A, CTRL, None),
+        ];
+
+        for (key, modifiers, expected) in cases {
+            assert_eq!(
+                clipboard_action(key, modifiers),
+                expected,
+                "{:?} {:?}",
+                key,
+                modifiers
+            );
+        }
+    }
+
+    #[test]
+    fn releases_are_recognized() {
+        for msg in [
+            WM_KEYUP,
+            WM_SYSKEYUP,
+            WM_LBUTTONUP,
+            WM_RBUTTONUP,
+            WM_MBUTTONUP,
+            WM_XBUTTONUP,
+        ] {
+            assert!(is_release(msg), "msg {:#x}", msg);
+        }
+        for msg in [
+            WM_KEYDOWN,
+            WM_SYSKEYDOWN,
+            WM_LBUTTONDOWN,
+            WM_LBUTTONDBLCLK,
+            WM_XBUTTONDOWN,
+            WM_CHAR,
+            WM_MOUSEMOVE,
+        ] {
+            assert!(!is_release(msg), "msg {:#x}", msg);
+        }
+    }
+}
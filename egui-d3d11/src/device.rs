@@ -0,0 +1,143 @@
+use crate::{
+    buffers::MeshBuffers,
+    error::{Error, Result},
+    pipeline::PipelineStates,
+    sampler::SamplerCache,
+    shader::CompiledShaders,
+};
+use windows::{
+    core::{IUnknown, Interface},
+    Win32::Graphics::{
+        Direct3D::D3D11_PRIMITIVE_TOPOLOGY_TRIANGLELIST,
+        Direct3D11::{
+            ID3D11Device, ID3D11DeviceContext, ID3D11InputLayout, ID3D11RenderTargetView,
+            ID3D11Texture2D, D3D11_APPEND_ALIGNED_ELEMENT, D3D11_INPUT_ELEMENT_DESC,
+            D3D11_INPUT_PER_VERTEX_DATA, D3D11_TEXTURE2D_DESC, D3D11_VIEWPORT,
+        },
+        Dxgi::{
+            Common::{DXGI_FORMAT_R32G32B32A32_FLOAT, DXGI_FORMAT_R32G32_FLOAT},
+            IDXGISwapChain,
+        },
+    },
+};
+
+/// Everything created from the game's device, except for textures.
+/// Thrown away as a whole once the device is lost or replaced.
+pub struct DeviceObjects {
+    pub device: ID3D11Device,
+    pub render_view: Option<ID3D11RenderTargetView>,
+    pub input_layout: ID3D11InputLayout,
+    pub shaders: CompiledShaders,
+    pub states: PipelineStates,
+    pub buffers: MeshBuffers,
+    pub samplers: SamplerCache,
+}
+
+impl DeviceObjects {
+    const INPUT_ELEMENTS_DESC: [D3D11_INPUT_ELEMENT_DESC; 3] = [
+        D3D11_INPUT_ELEMENT_DESC {
+            SemanticName: pc_str!("POSITION"),
+            SemanticIndex: 0,
+            Format: DXGI_FORMAT_R32G32_FLOAT,
+            InputSlot: 0,
+            AlignedByteOffset: 0,
+            InputSlotClass: D3D11_INPUT_PER_VERTEX_DATA,
+            InstanceDataStepRate: 0,
+        },
+        D3D11_INPUT_ELEMENT_DESC {
+            SemanticName: pc_str!("TEXCOORD"),
+            SemanticIndex: 0,
+            Format: DXGI_FORMAT_R32G32_FLOAT,
+            InputSlot: 0,
+            AlignedByteOffset: D3D11_APPEND_ALIGNED_ELEMENT,
+            InputSlotClass: D3D11_INPUT_PER_VERTEX_DATA,
+            InstanceDataStepRate: 0,
+        },
+        D3D11_INPUT_ELEMENT_DESC {
+            SemanticName: pc_str!("COLOR"),
+            SemanticIndex: 0,
+            Format: DXGI_FORMAT_R32G32B32A32_FLOAT,
+            InputSlot: 0,
+            AlignedByteOffset: D3D11_APPEND_ALIGNED_ELEMENT,
+            InputSlotClass: D3D11_INPUT_PER_VERTEX_DATA,
+            InstanceDataStepRate: 0,
+        },
+    ];
+
+    /// Render target view is left empty, see [`Self::create_render_view`].
+    pub fn new(device: ID3D11Device) -> Result<Self> {
+        unsafe {
+            let shaders = CompiledShaders::new(&device)?;
+            let input_layout = device
+                .CreateInputLayout(&Self::INPUT_ELEMENTS_DESC, shaders.bytecode())
+                .map_err(|e| Error::Device("Failed to create input layout", e))?;
+            let states = PipelineStates::new(&device)?;
+
+            Ok(Self {
+                buffers: MeshBuffers::default(),
+                samplers: SamplerCache::default(),
+                render_view: None,
+                input_layout,
+                shaders,
+                states,
+                device,
+            })
+        }
+    }
+
+    /// Creates view of the swapchain's backbuffer, returns the backbuffer's size in pixels.
+    pub fn create_render_view(&mut self, swap: &IDXGISwapChain) -> Result<(f32, f32)> {
+        unsafe {
+            let backbuffer: ID3D11Texture2D = swap
+                .GetBuffer(0)
+                .map_err(|e| Error::Device("Failed to get swapchain's backbuffer", e))?;
+
+            let mut desc = D3D11_TEXTURE2D_DESC::default();
+            backbuffer.GetDesc(&mut desc);
+
+            self.render_view = Some(
+                self.device
+                    .CreateRenderTargetView(backbuffer, 0 as _)
+                    .map_err(|e| Error::Device("Failed to create render target view", e))?,
+            );
+
+            Ok((desc.Width as f32, desc.Height as f32))
+        }
+    }
+
+    /// Checks if the render view was created from `swap`'s current backbuffer.
+    /// It isn't once the game replaces its swapchain while keeping the device.
+    pub fn targets(&self, swap: &IDXGISwapChain) -> bool {
+        unsafe {
+            let view = match &self.render_view {
+                Some(view) => view,
+                None => return false,
+            };
+
+            let mut resource = None;
+            view.GetResource(&mut resource);
+
+            // Only `IUnknown` pointers are guaranteed to be equal for the same object.
+            match (resource, swap.GetBuffer::<IUnknown>(0)) {
+                (Some(resource), Ok(backbuffer)) => {
+                    resource.cast::<IUnknown>().ok() == Some(backbuffer)
+                }
+                _ => false,
+            }
+        }
+    }
+
+    /// Binds everything needed to draw egui's meshes.
+    pub fn bind(&self, ctx: &ID3D11DeviceContext, viewport: D3D11_VIEWPORT) {
+        unsafe {
+            self.states.bind(ctx);
+            ctx.RSSetViewports(&[viewport]);
+            ctx.OMSetRenderTargets(&[self.render_view.clone()], None);
+            ctx.IASetPrimitiveTopology(D3D11_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
+            ctx.IASetInputLayout(&self.input_layout);
+            self.buffers.bind(ctx);
+            ctx.VSSetShader(&self.shaders.vertex, &[]);
+            ctx.PSSetShader(&self.shaders.pixel, &[]);
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use egui::TextureId;
+use thiserror::Error;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Failures reported by `try_*` methods of [`crate::DirectX11App`].
+/// Direct3D failures carry a short description of the failed call and the original error.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("You must call init only once")]
+    AlreadyInitialized,
+    #[error("You need to call init first")]
+    NotInitialized,
+    #[error("Invalid output window descriptor")]
+    InvalidWindow,
+    /// Querying swapchain, device or its context, or creating render target view or input layout.
+    #[error("{0}: {1}")]
+    Device(&'static str, #[source] windows::core::Error),
+    #[error("{0}: {1}")]
+    Buffer(&'static str, #[source] windows::core::Error),
+    #[error("{0}: {1}")]
+    Texture(&'static str, #[source] windows::core::Error),
+    #[error("{0}: {1}")]
+    Shader(&'static str, #[source] windows::core::Error),
+    /// Compiler's output, only with `force-compile` feature.
+    #[error("Failed to compile shader: {0}")]
+    ShaderCompile(String),
+    /// egui tried to update a texture that was never allocated.
+    #[error("Texture {0:?} was never allocated")]
+    UnknownTexture(TextureId),
+    /// egui tried to allocate a texture with zero width or height.
+    #[error("Texture {0:?} has no pixels")]
+    EmptyTexture(TextureId),
+    /// Rasterizer, sampler, blend or depth stencil state.
+    #[error("{0}: {1}")]
+    State(&'static str, #[source] windows::core::Error),
+}
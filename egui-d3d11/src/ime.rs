@@ -0,0 +1,85 @@
+//! Communication with the input method editor.
+//! Only Win32 calls live here, translation of composition strings is done in [`crate::decode`].
+use crate::decode::{Composition, GCS_COMPSTR, GCS_RESULTSTR};
+use egui::Pos2;
+use std::{ffi::c_void, mem::size_of};
+use windows::Win32::{
+    Foundation::{HWND, POINT, RECT},
+    UI::Input::Ime::{
+        ImmGetCompositionStringW, ImmGetContext, ImmReleaseContext, ImmSetCandidateWindow,
+        ImmSetCompositionWindow, CANDIDATEFORM, COMPOSITIONFORM,
+    },
+};
+
+const CFS_POINT: u32 = 0x0002;
+const CFS_CANDIDATEPOS: u32 = 0x0040;
+
+/// Reads strings announced by `lparam` of `WM_IME_COMPOSITION`.
+/// Must be called from the window's thread.
+pub fn read_composition(hwnd: HWND, lparam: isize) -> Composition {
+    unsafe {
+        let himc = ImmGetContext(hwnd);
+        if himc.0 == 0 {
+            return Composition::default();
+        }
+
+        let read = |flag: u32| {
+            if lparam as u32 & flag == 0 {
+                return None;
+            }
+
+            let len = ImmGetCompositionStringW(himc, flag, std::ptr::null_mut(), 0);
+            if len < 0 {
+                return None;
+            }
+
+            let mut buf = vec![0u16; len as usize / size_of::<u16>()];
+            ImmGetCompositionStringW(himc, flag, buf.as_mut_ptr() as *mut c_void, len as _);
+            Some(String::from_utf16_lossy(&buf))
+        };
+
+        let composition = Composition {
+            composition: read(GCS_COMPSTR),
+            result: read(GCS_RESULTSTR),
+        };
+
+        ImmReleaseContext(hwnd, himc);
+        composition
+    }
+}
+
+/// Moves composition and candidate windows next to the text cursor.
+/// `pos` is in client coordinates. Must be called from the window's thread.
+pub fn set_ime_position(hwnd: HWND, pos: Pos2) {
+    let point = POINT {
+        x: pos.x as _,
+        y: pos.y as _,
+    };
+
+    unsafe {
+        let himc = ImmGetContext(hwnd);
+        if himc.0 == 0 {
+            return;
+        }
+
+        ImmSetCompositionWindow(
+            himc,
+            &COMPOSITIONFORM {
+                dwStyle: CFS_POINT,
+                ptCurrentPos: point,
+                rcArea: RECT::default(),
+            },
+        );
+        ImmSetCandidateWindow(
+            himc,
+            &CANDIDATEFORM {
+                dwIndex: 0,
+                dwStyle: CFS_CANDIDATEPOS,
+                ptCurrentPos: point,
+                rcArea: RECT::default(),
+            },
+        );
+
+        ImmReleaseContext(hwnd, himc);
+    }
+}
@@ -1,33 +1,75 @@
 #![allow(dead_code)]
 
-use clipboard::{windows_clipboard::WindowsClipboardContext, ClipboardProvider};
-use egui::{Event, Key, Modifiers, PointerButton, Pos2, RawInput, Rect, Vec2};
+use crate::{
+    clipboard::{paste_event, ClipboardBackend},
+    decode::{
+        classify_message, decode_composition, dpi_to_pixels_per_point, get_size, is_focus_lost,
+        key_releases, map_pointer_pos, KeyboardSnapshot, MessageDecoder, WM_DPICHANGED,
+        WM_IME_COMPOSITION, WM_IME_STARTCOMPOSITION, WM_MOUSELEAVE, WM_MOUSEMOVE, WM_SETFOCUS,
+        WM_SIZE,
+    },
+    ime::{read_composition, set_ime_position},
+    keymap::ModifierState,
+    mapping::CoordinateMapping,
+};
+use egui::{Event, Key, Modifiers, Pos2, RawInput, Rect, Vec2};
+use std::mem::size_of;
 use windows::Win32::{
     Foundation::{HWND, RECT},
     System::WindowsProgramming::NtQuerySystemTime,
     UI::{
-        Input::KeyboardAndMouse::{
-            GetAsyncKeyState, VIRTUAL_KEY, VK_BACK, VK_CONTROL, VK_DELETE, VK_DOWN, VK_END,
-            VK_ESCAPE, VK_HOME, VK_INSERT, VK_LEFT, VK_LSHIFT, VK_NEXT, VK_PRIOR, VK_RETURN,
-            VK_RIGHT, VK_SPACE, VK_TAB, VK_UP,
-        },
-        WindowsAndMessaging::{
-            GetClientRect, MK_CONTROL, MK_SHIFT, WHEEL_DELTA, WM_CHAR, WM_KEYDOWN, WM_KEYUP,
-            WM_LBUTTONDBLCLK, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDBLCLK, WM_MBUTTONDOWN,
-            WM_MBUTTONUP, WM_MOUSEHWHEEL, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_RBUTTONDBLCLK,
-            WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SYSKEYDOWN, WM_SYSKEYUP,
-        },
+        HiDpi::GetDpiForWindow,
+        Input::KeyboardAndMouse::{GetKeyState, TrackMouseEvent, TME_LEAVE, TRACKMOUSEEVENT},
+        WindowsAndMessaging::GetClientRect,
     },
 };
 
 pub struct InputCollector {
     hwnd: HWND,
     events: Vec<Event>,
-    modifiers: Option<Modifiers>,
+    modifiers: ModifierState,
+    resync_modifiers: bool,
+    held_keys: Vec<Key>,
+    tracking_leave: bool,
+    toggle: Option<Hotkey>,
+    visible: bool,
+    text_cursor_pos: Option<Pos2>,
+    decoder: MessageDecoder,
+    clipboard: Box<dyn ClipboardBackend>,
+    native_pixels_per_point: f32,
+    pixels_per_point: Option<f32>,
+    client_size: Vec2,
+    backbuffer_size: Vec2,
+    mapping: CoordinateMapping,
+}
+
+/// Set in `lparam` of `WM_KEYDOWN` if the key was already down, i.e. for auto-repeat.
+const KEY_REPEAT_FLAG: isize = 1 << 30;
+
+/// Key combination that shows and hides the overlay.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hotkey {
+    pub key: Key,
+    pub modifiers: Modifiers,
+}
+
+impl Hotkey {
+    /// Hotkey without any modifiers.
+    pub const fn new(key: Key) -> Self {
+        Self {
+            key,
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    pub const fn with_modifiers(key: Key, modifiers: Modifiers) -> Self {
+        Self { key, modifiers }
+    }
 }
 
 /// High-level overview of recognized `WndProc` messages.
 #[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum InputResult {
     Unknown,
     MouseMove,
@@ -38,6 +80,14 @@ pub enum InputResult {
     Scroll,
     Zoom,
     Key,
+    MouseExtra,
+    MouseLeave,
+    Focus,
+    Toggle,
+    Cursor,
+    /// One of `WM_IME_*COMPOSITION` messages. Once text is committed, `WM_IME_COMPOSITION`
+    /// must not reach `DefWindowProc`, otherwise it comes once again as `WM_CHAR`.
+    Composition,
 }
 
 impl InputResult {
@@ -50,178 +100,251 @@ impl InputResult {
     pub fn is_unknown(&self) -> bool {
         matches!(*self, InputResult::Unknown)
     }
+
+    /// Message comes from the mouse.
+    #[inline]
+    pub fn is_pointer(&self) -> bool {
+        matches!(
+            *self,
+            InputResult::MouseMove
+                | InputResult::MouseLeft
+                | InputResult::MouseRight
+                | InputResult::MouseMiddle
+                | InputResult::MouseExtra
+                | InputResult::Scroll
+                | InputResult::Zoom
+        )
+    }
+
+    /// Message comes from the keyboard.
+    #[inline]
+    pub fn is_keyboard(&self) -> bool {
+        matches!(
+            *self,
+            InputResult::Key
+                | InputResult::Character
+                | InputResult::Toggle
+                | InputResult::Composition
+        )
+    }
 }
 
 impl InputCollector {
-    pub fn new(hwnd: HWND) -> Self {
+    pub fn new(hwnd: HWND, clipboard: Box<dyn ClipboardBackend>, backbuffer_size: Vec2) -> Self {
+        let mut client = RECT::default();
+        unsafe {
+            GetClientRect(hwnd, &mut client);
+        }
+
         Self {
             hwnd,
             events: vec![],
-            modifiers: None,
+            modifiers: ModifierState::default(),
+            resync_modifiers: true,
+            held_keys: vec![],
+            tracking_leave: false,
+            toggle: None,
+            visible: true,
+            text_cursor_pos: None,
+            decoder: MessageDecoder::default(),
+            clipboard,
+            native_pixels_per_point: dpi_to_pixels_per_point(unsafe { GetDpiForWindow(hwnd) }),
+            pixels_per_point: None,
+            client_size: Vec2::new(
+                (client.right - client.left) as f32,
+                (client.bottom - client.top) as f32,
+            ),
+            backbuffer_size,
+            mapping: CoordinateMapping::default(),
+        }
+    }
+
+    #[inline]
+    pub fn set_toggle_key(&mut self, toggle: Option<Hotkey>) {
+        self.toggle = toggle;
+    }
+
+    #[inline]
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Hidden overlay doesn't collect any events.
+    /// Keys held when hiding are released right away, their real releases are dropped.
+    #[inline]
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+        if !visible {
+            self.events.clear();
+            // Toggle key never reaches egui, so there's nothing to release.
+            let held: Vec<_> = self
+                .held_keys
+                .iter()
+                .copied()
+                .filter(|&key| self.toggle.map_or(true, |t| t.key != key))
+                .collect();
+            self.events.extend(key_releases(&held));
         }
     }
 
+    /// Puts text copied or cut in the ui into the clipboard.
+    #[inline]
+    pub fn set_clipboard_text(&mut self, text: String) {
+        self.clipboard.set(text);
+    }
+
+    /// Scale of the ui, either set by the user or taken from the window's DPI.
+    #[inline]
+    pub fn pixels_per_point(&self) -> f32 {
+        self.pixels_per_point
+            .unwrap_or(self.native_pixels_per_point)
+    }
+
+    /// Overrides the scale of the ui, `None` goes back to the window's DPI.
+    #[inline]
+    pub fn set_pixels_per_point(&mut self, pixels_per_point: Option<f32>) {
+        self.pixels_per_point = pixels_per_point;
+    }
+
+    /// Size of the backbuffer in pixels, ui covers all of it.
+    #[inline]
+    pub fn set_backbuffer_size(&mut self, size: Vec2) {
+        self.backbuffer_size = size;
+    }
+
+    #[inline]
+    pub fn set_coordinate_mapping(&mut self, mapping: CoordinateMapping) {
+        self.mapping = mapping;
+    }
+
+    /// Where IME windows should be placed, in points.
+    #[inline]
+    pub fn set_text_cursor_pos(&mut self, pos: Option<Pos2>) {
+        self.text_cursor_pos = pos;
+    }
+
+    /// Whether modifiers should be re-read with `GetKeyState` when window gains focus.
+    /// Modifiers released while the window was in background are missed otherwise.
+    #[inline]
+    pub fn set_resync_modifiers(&mut self, resync: bool) {
+        self.resync_modifiers = resync;
+    }
+
     pub fn process(&mut self, umsg: u32, wparam: usize, lparam: isize) -> InputResult {
         match umsg {
-            WM_MOUSEMOVE => {
-                self.events.push(Event::PointerMoved(get_pos(lparam)));
-                InputResult::MouseMove
-            }
-            WM_LBUTTONDOWN | WM_LBUTTONDBLCLK => {
-                let modifiers = get_mouse_modifiers(wparam);
-                self.modifiers = Some(modifiers);
-
-                self.events.push(Event::PointerButton {
-                    pos: get_pos(lparam),
-                    button: PointerButton::Primary,
-                    pressed: true,
-                    modifiers,
-                });
-                InputResult::MouseLeft
-            }
-            WM_LBUTTONUP => {
-                let modifiers = get_mouse_modifiers(wparam);
-                self.modifiers = Some(modifiers);
-
-                self.events.push(Event::PointerButton {
-                    pos: get_pos(lparam),
-                    button: PointerButton::Primary,
-                    pressed: false,
-                    modifiers,
-                });
-                InputResult::MouseLeft
-            }
-            WM_RBUTTONDOWN | WM_RBUTTONDBLCLK => {
-                let modifiers = get_mouse_modifiers(wparam);
-                self.modifiers = Some(modifiers);
-
-                self.events.push(Event::PointerButton {
-                    pos: get_pos(lparam),
-                    button: PointerButton::Secondary,
-                    pressed: true,
-                    modifiers
-                });
-                InputResult::MouseRight
+            WM_SETFOCUS if self.resync_modifiers => {
+                self.modifiers
+                    .resync(|vk| unsafe { GetKeyState(vk as _) } < 0);
             }
-            WM_RBUTTONUP => {
-                let modifiers = get_mouse_modifiers(wparam);
-                self.modifiers = Some(modifiers);
-
-                self.events.push(Event::PointerButton {
-                    pos: get_pos(lparam),
-                    button: PointerButton::Secondary,
-                    pressed: false,
-                    modifiers
-                });
-                InputResult::MouseRight
+            WM_MOUSEMOVE if !self.tracking_leave => self.track_mouse_leave(),
+            WM_MOUSELEAVE => self.tracking_leave = false,
+            WM_SIZE => self.client_size = get_size(lparam),
+            WM_DPICHANGED => {
+                self.native_pixels_per_point = dpi_to_pixels_per_point(wparam as u32 & 0xFFFF);
             }
-            WM_MBUTTONDOWN | WM_MBUTTONDBLCLK => {
-                let modifiers = get_mouse_modifiers(wparam);
-                self.modifiers = Some(modifiers);
-
-                self.events.push(Event::PointerButton {
-                    pos: get_pos(lparam),
-                    button: PointerButton::Middle,
-                    pressed: true,
-                    modifiers
-                });
-                InputResult::MouseMiddle
-            }
-            WM_MBUTTONUP => {
-                let modifiers = get_mouse_modifiers(wparam);
-                self.modifiers = Some(modifiers);
-
-                self.events.push(Event::PointerButton {
-                    pos: get_pos(lparam),
-                    button: PointerButton::Middle,
-                    pressed: false,
-                    modifiers
-                });
-                InputResult::MouseMiddle
-            }
-            WM_CHAR => {
-                if let Some(ch) = char::from_u32(wparam as _) {
-                    if !ch.is_control() {
-                        self.events.push(Event::Text(ch.into()));
-                    }
+            WM_IME_STARTCOMPOSITION | WM_IME_COMPOSITION if self.visible => {
+                if let Some(pos) = self.text_cursor_pos {
+                    let ppp = self.pixels_per_point();
+                    let pos = self.mapping.to_client(
+                        Pos2::new(pos.x * ppp, pos.y * ppp),
+                        self.client_size,
+                        self.backbuffer_size,
+                    );
+                    set_ime_position(self.hwnd, pos);
                 }
-                InputResult::Character
             }
-            WM_MOUSEWHEEL => {
-                let delta = (wparam >> 16) as i16 as f32 * 10. / WHEEL_DELTA as f32;
-
-                if wparam & MK_CONTROL as usize != 0 {
-                    self.events
-                        .push(Event::Zoom(if delta > 0. { 1.5 } else { 0.5 }));
-                    InputResult::Zoom
-                } else {
-                    self.events.push(Event::Scroll(Vec2::new(0., delta)));
-                    InputResult::Scroll
-                }
+            _ => {}
+        }
+
+        if umsg == WM_IME_COMPOSITION {
+            if self.visible {
+                let composition = read_composition(self.hwnd, lparam);
+                self.events.extend(decode_composition(&composition));
             }
-            WM_MOUSEHWHEEL => {
-                let delta = (wparam >> 16) as i16 as f32 * 10. / WHEEL_DELTA as f32;
-
-                if wparam & MK_CONTROL as usize != 0 {
-                    self.events
-                        .push(Event::Zoom(if delta > 0. { 1.5 } else { 0.5 }));
-                    InputResult::Zoom
-                } else {
-                    self.events.push(Event::Scroll(Vec2::new(delta, 0.)));
-                    InputResult::Scroll
+            return InputResult::Composition;
+        }
+
+        self.modifiers.update(umsg, wparam, lparam);
+        let keyboard = KeyboardSnapshot {
+            modifiers: self.modifiers.modifiers(),
+            held_keys: self.held_keys.clone(),
+        };
+
+        let mut toggled = false;
+        let from_key = classify_message(umsg, wparam) == InputResult::Key;
+        let ppp = self.pixels_per_point();
+        for event in self.decoder.decode(umsg, wparam, lparam, &keyboard) {
+            let event = map_pointer_pos(event, |pos| {
+                let pos = self
+                    .mapping
+                    .to_backbuffer(pos, self.client_size, self.backbuffer_size);
+                Pos2::new(pos.x / ppp, pos.y / ppp)
+            });
+
+            if let Event::Key {
+                key,
+                pressed,
+                modifiers,
+            } = event
+            {
+                if !pressed {
+                    self.held_keys.retain(|&k| k != key);
+                } else if !self.held_keys.contains(&key) {
+                    self.held_keys.push(key);
                 }
-            }
-            msg @ (WM_KEYDOWN | WM_SYSKEYDOWN) => {
-                let modifiers = get_key_modifiers(msg);
-                self.modifiers = Some(modifiers);
-
-                if let Some(key) = get_key(wparam) {
-                    if key == Key::V && modifiers.ctrl {
-                        if let Some(clipboard) = get_clipboard_text() {
-                            self.events.push(Event::Text(clipboard));
+
+                if self.is_toggle(key, pressed, modifiers) {
+                    // Releases made up on focus loss aren't a toggle,
+                    // the game has to see the focus change.
+                    if from_key {
+                        if pressed && lparam & KEY_REPEAT_FLAG == 0 {
+                            self.set_visible(!self.visible);
                         }
-                    }
-                    
-                    if key == Key::C && modifiers.ctrl {
-                        self.events.push(Event::Copy);
+                        toggled = true;
                     }
 
-                    if key == Key::X && modifiers.ctrl {
-                        self.events.push(Event::Cut);
-                    }
+                    // Toggle key never reaches egui.
+                    continue;
+                }
 
-                    self.events.push(Event::Key {
-                        pressed: true,
-                        modifiers,
-                        key,
-                    });
+                if pressed && self.visible {
+                    if let Some(paste) = paste_event(key, modifiers, self.clipboard.as_mut()) {
+                        self.events.push(paste);
+                    }
                 }
-                InputResult::Key
             }
-            msg @ (WM_KEYUP | WM_SYSKEYUP) => {
-                let modifiers = get_key_modifiers(msg);
-                self.modifiers = Some(modifiers);
-
-                if let Some(key) = get_key(wparam) {
-                    self.events.push(Event::Key {
-                        pressed: false,
-                        modifiers,
-                        key,
-                    });
-                }
-                InputResult::Key
+
+            if self.visible {
+                self.events.push(event);
             }
-            _ => InputResult::Unknown,
+        }
+
+        if is_focus_lost(umsg, wparam) {
+            self.modifiers = ModifierState::default();
+        }
+
+        if toggled {
+            InputResult::Toggle
+        } else {
+            classify_message(umsg, wparam)
+        }
+    }
+
+    /// Key releases are matched without modifiers, they could've been released first.
+    fn is_toggle(&self, key: Key, pressed: bool, modifiers: Modifiers) -> bool {
+        match self.toggle {
+            Some(toggle) if toggle.key == key => !pressed || modifiers.matches(toggle.modifiers),
+            _ => false,
         }
     }
 
     pub fn collect_input(&mut self) -> RawInput {
         RawInput {
-            modifiers: self.modifiers.take().unwrap_or_default(),
+            modifiers: self.modifiers.modifiers(),
             events: std::mem::take(&mut self.events),
             screen_rect: Some(self.get_screen_rect()),
             time: Some(Self::get_system_time()),
-            pixels_per_point: Some(1.),
+            pixels_per_point: Some(self.pixels_per_point()),
             max_texture_side: None,
             predicted_dt: 1. / 60.,
             hovered_files: vec![],
@@ -243,83 +366,27 @@ impl InputCollector {
         (time as f64) / 10_000_000.
     }
 
-    #[inline]
-    pub fn get_screen_size(&self) -> Pos2 {
-        let mut rect = RECT::default();
-        unsafe {
-            GetClientRect(self.hwnd, &mut rect);
-        }
+    /// Asks windows to send `WM_MOUSELEAVE` once cursor leaves the window.
+    fn track_mouse_leave(&mut self) {
+        let mut event = TRACKMOUSEEVENT {
+            cbSize: size_of::<TRACKMOUSEEVENT>() as _,
+            dwFlags: TME_LEAVE,
+            hwndTrack: self.hwnd,
+            dwHoverTime: 0,
+        };
 
-        Pos2::new(
-            (rect.right - rect.left) as f32,
-            (rect.bottom - rect.top) as f32,
-        )
+        self.tracking_leave = unsafe { TrackMouseEvent(&mut event) }.as_bool();
     }
 
+    /// Screen rect in points.
     #[inline]
     pub fn get_screen_rect(&self) -> Rect {
+        let size = self.backbuffer_size;
+        let ppp = self.pixels_per_point();
+
         Rect {
             min: Pos2::ZERO,
-            max: self.get_screen_size(),
+            max: Pos2::new(size.x / ppp, size.y / ppp),
         }
     }
 }
-
-fn get_pos(lparam: isize) -> Pos2 {
-    let x = (lparam & 0xFFFF) as i16 as f32;
-    let y = (lparam >> 16 & 0xFFFF) as i16 as f32;
-
-    Pos2::new(x, y)
-}
-
-fn get_mouse_modifiers(wparam: usize) -> Modifiers {
-    Modifiers {
-        alt: false,
-        ctrl: (wparam & MK_CONTROL as usize) != 0,
-        shift: (wparam & MK_SHIFT as usize) != 0,
-        mac_cmd: false,
-        command: (wparam & MK_CONTROL as usize) != 0,
-    }
-}
-
-fn get_key_modifiers(msg: u32) -> Modifiers {
-    let ctrl = unsafe { GetAsyncKeyState(VK_CONTROL.0 as _) != 0 };
-    let shift = unsafe { GetAsyncKeyState(VK_LSHIFT.0 as _) != 0 };
-
-    Modifiers {
-        alt: msg == WM_SYSKEYDOWN,
-        mac_cmd: false,
-        command: ctrl,
-        shift,
-        ctrl,
-    }
-}
-
-fn get_key(wparam: usize) -> Option<Key> {
-    match wparam {
-        0x30..=0x39 => unsafe { Some(std::mem::transmute::<_, Key>(wparam as u8 - 0x21)) },
-        0x41..=0x5A => unsafe { Some(std::mem::transmute::<_, Key>(wparam as u8 - 0x28)) },
-        _ => match VIRTUAL_KEY(wparam as u16) {
-            VK_DOWN => Some(Key::ArrowDown),
-            VK_LEFT => Some(Key::ArrowLeft),
-            VK_RIGHT => Some(Key::ArrowRight),
-            VK_UP => Some(Key::ArrowUp),
-            VK_ESCAPE => Some(Key::Escape),
-            VK_TAB => Some(Key::Tab),
-            VK_BACK => Some(Key::Backspace),
-            VK_RETURN => Some(Key::Enter),
-            VK_SPACE => Some(Key::Space),
-            VK_INSERT => Some(Key::Insert),
-            VK_DELETE => Some(Key::Delete),
-            VK_HOME => Some(Key::Home),
-            VK_END => Some(Key::End),
-            VK_PRIOR => Some(Key::PageUp),
-            VK_NEXT => Some(Key::PageDown),
-            _ => None,
-        },
-    }
-}
-
-fn get_clipboard_text() -> Option<String> {
-    WindowsClipboardContext.get_contents().ok()
-}
@@ -0,0 +1,412 @@
+//! Mapping of virtual-key codes onto [`egui::Key`].
+//! Every supported code is listed explicitly, so reordering of egui's enum can't break it.
+use crate::decode::{WM_KEYDOWN, WM_KEYUP, WM_SYSKEYDOWN, WM_SYSKEYUP};
+use egui::{Key, Modifiers};
+
+/// Set in `lparam` of keyboard messages for keys from the extended part of the keyboard.
+pub const EXTENDED_KEY_FLAG: isize = 1 << 24;
+
+/// Scan code of the right shift, left and right shifts share `VK_SHIFT`.
+const RIGHT_SHIFT_SCAN_CODE: isize = 0x36;
+
+pub const VK_BACK: u16 = 0x08;
+pub const VK_TAB: u16 = 0x09;
+pub const VK_RETURN: u16 = 0x0D;
+pub const VK_ESCAPE: u16 = 0x1B;
+pub const VK_SHIFT: u16 = 0x10;
+pub const VK_CONTROL: u16 = 0x11;
+pub const VK_MENU: u16 = 0x12;
+pub const VK_SPACE: u16 = 0x20;
+pub const VK_PRIOR: u16 = 0x21;
+pub const VK_NEXT: u16 = 0x22;
+pub const VK_END: u16 = 0x23;
+pub const VK_HOME: u16 = 0x24;
+pub const VK_LEFT: u16 = 0x25;
+pub const VK_UP: u16 = 0x26;
+pub const VK_RIGHT: u16 = 0x27;
+pub const VK_DOWN: u16 = 0x28;
+pub const VK_INSERT: u16 = 0x2D;
+pub const VK_DELETE: u16 = 0x2E;
+pub const VK_NUMPAD0: u16 = 0x60;
+pub const VK_NUMPAD9: u16 = 0x69;
+pub const VK_ADD: u16 = 0x6B;
+pub const VK_SUBTRACT: u16 = 0x6D;
+pub const VK_F1: u16 = 0x70;
+pub const VK_F20: u16 = 0x83;
+pub const VK_LSHIFT: u16 = 0xA0;
+pub const VK_RSHIFT: u16 = 0xA1;
+pub const VK_LCONTROL: u16 = 0xA2;
+pub const VK_RCONTROL: u16 = 0xA3;
+pub const VK_LMENU: u16 = 0xA4;
+pub const VK_RMENU: u16 = 0xA5;
+pub const VK_OEM_PLUS: u16 = 0xBB;
+pub const VK_OEM_MINUS: u16 = 0xBD;
+
+/// Keys from the main part of the keyboard.
+pub const KEYS: &[(u16, Key)] = &[
+    (VK_DOWN, Key::ArrowDown),
+    (VK_LEFT, Key::ArrowLeft),
+    (VK_RIGHT, Key::ArrowRight),
+    (VK_UP, Key::ArrowUp),
+    (VK_ESCAPE, Key::Escape),
+    (VK_TAB, Key::Tab),
+    (VK_BACK, Key::Backspace),
+    (VK_RETURN, Key::Enter),
+    (VK_SPACE, Key::Space),
+    (VK_INSERT, Key::Insert),
+    (VK_DELETE, Key::Delete),
+    (VK_HOME, Key::Home),
+    (VK_END, Key::End),
+    (VK_PRIOR, Key::PageUp),
+    (VK_NEXT, Key::PageDown),
+    (VK_OEM_MINUS, Key::Minus),
+    (VK_OEM_PLUS, Key::PlusEquals),
+    (0x30, Key::Num0),
+    (0x31, Key::Num1),
+    (0x32, Key::Num2),
+    (0x33, Key::Num3),
+    (0x34, Key::Num4),
+    (0x35, Key::Num5),
+    (0x36, Key::Num6),
+    (0x37, Key::Num7),
+    (0x38, Key::Num8),
+    (0x39, Key::Num9),
+    (0x41, Key::A),
+    (0x42, Key::B),
+    (0x43, Key::C),
+    (0x44, Key::D),
+    (0x45, Key::E),
+    (0x46, Key::F),
+    (0x47, Key::G),
+    (0x48, Key::H),
+    (0x49, Key::I),
+    (0x4A, Key::J),
+    (0x4B, Key::K),
+    (0x4C, Key::L),
+    (0x4D, Key::M),
+    (0x4E, Key::N),
+    (0x4F, Key::O),
+    (0x50, Key::P),
+    (0x51, Key::Q),
+    (0x52, Key::R),
+    (0x53, Key::S),
+    (0x54, Key::T),
+    (0x55, Key::U),
+    (0x56, Key::V),
+    (0x57, Key::W),
+    (0x58, Key::X),
+    (0x59, Key::Y),
+    (0x5A, Key::Z),
+    (VK_F1, Key::F1),
+    (0x71, Key::F2),
+    (0x72, Key::F3),
+    (0x73, Key::F4),
+    (0x74, Key::F5),
+    (0x75, Key::F6),
+    (0x76, Key::F7),
+    (0x77, Key::F8),
+    (0x78, Key::F9),
+    (0x79, Key::F10),
+    (0x7A, Key::F11),
+    (0x7B, Key::F12),
+    (0x7C, Key::F13),
+    (0x7D, Key::F14),
+    (0x7E, Key::F15),
+    (0x7F, Key::F16),
+    (0x80, Key::F17),
+    (0x81, Key::F18),
+    (0x82, Key::F19),
+    (VK_F20, Key::F20),
+];
+
+/// Keys coming from the numeric keypad.
+/// Without NumLock keypad sends navigation codes, those are told apart
+/// from the dedicated navigation block by the missing extended key flag.
+pub const NUMPAD_KEYS: &[(u16, Key)] = &[
+    (VK_NUMPAD0, Key::Num0),
+    (0x61, Key::Num1),
+    (0x62, Key::Num2),
+    (0x63, Key::Num3),
+    (0x64, Key::Num4),
+    (0x65, Key::Num5),
+    (0x66, Key::Num6),
+    (0x67, Key::Num7),
+    (0x68, Key::Num8),
+    (VK_NUMPAD9, Key::Num9),
+    (VK_ADD, Key::PlusEquals),
+    (VK_SUBTRACT, Key::Minus),
+    (VK_RETURN, Key::Enter),
+    (VK_INSERT, Key::Insert),
+    (VK_DELETE, Key::Delete),
+    (VK_HOME, Key::Home),
+    (VK_END, Key::End),
+    (VK_PRIOR, Key::PageUp),
+    (VK_NEXT, Key::PageDown),
+    (VK_DOWN, Key::ArrowDown),
+    (VK_LEFT, Key::ArrowLeft),
+    (VK_RIGHT, Key::ArrowRight),
+    (VK_UP, Key::ArrowUp),
+];
+
+/// Checks if the key of a keyboard message was pressed on the numeric keypad.
+pub fn is_numpad_key(vk: u16, lparam: isize) -> bool {
+    let extended = lparam & EXTENDED_KEY_FLAG != 0;
+    match vk {
+        VK_NUMPAD0..=VK_NUMPAD9 | VK_ADD | VK_SUBTRACT => true,
+        // Keypad's Enter is the extended one.
+        VK_RETURN => extended,
+        // Dedicated navigation keys are extended, keypad ones are not.
+        VK_INSERT | VK_DELETE | VK_HOME | VK_END | VK_PRIOR | VK_NEXT | VK_DOWN | VK_LEFT
+        | VK_RIGHT | VK_UP => !extended,
+        _ => false,
+    }
+}
+
+/// Translates `wparam` and `lparam` of a keyboard message into egui's key.
+pub fn translate_key(wparam: usize, lparam: isize) -> Option<Key> {
+    let vk = wparam as u16;
+    let table = if is_numpad_key(vk, lparam) {
+        NUMPAD_KEYS
+    } else {
+        KEYS
+    };
+
+    table
+        .iter()
+        .find(|(code, _)| *code == vk)
+        .map(|(_, key)| *key)
+}
+
+/// Tracks held modifier keys from the keyboard messages themselves,
+/// so the state is exactly what the window has seen so far.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ModifierState {
+    pub left_shift: bool,
+    pub right_shift: bool,
+    pub left_ctrl: bool,
+    pub right_ctrl: bool,
+    pub left_alt: bool,
+    pub right_alt: bool,
+}
+
+impl ModifierState {
+    /// Feeds a window message into the state machine.
+    /// Returns `true` if the message was about one of the modifier keys.
+    pub fn update(&mut self, msg: u32, wparam: usize, lparam: isize) -> bool {
+        let pressed = match msg {
+            WM_KEYDOWN | WM_SYSKEYDOWN => true,
+            WM_KEYUP | WM_SYSKEYUP => false,
+            _ => return false,
+        };
+
+        let extended = lparam & EXTENDED_KEY_FLAG != 0;
+        let key = match wparam as u16 {
+            VK_SHIFT if (lparam >> 16) & 0xFF == RIGHT_SHIFT_SCAN_CODE => &mut self.right_shift,
+            VK_SHIFT | VK_LSHIFT => &mut self.left_shift,
+            VK_RSHIFT => &mut self.right_shift,
+            VK_CONTROL if extended => &mut self.right_ctrl,
+            VK_CONTROL | VK_LCONTROL => &mut self.left_ctrl,
+            VK_RCONTROL => &mut self.right_ctrl,
+            VK_MENU if extended => &mut self.right_alt,
+            VK_MENU | VK_LMENU => &mut self.left_alt,
+            VK_RMENU => &mut self.right_alt,
+            _ => return false,
+        };
+
+        *key = pressed;
+        true
+    }
+
+    /// Rebuilds the state from scratch, `is_down` is asked about each of `VK_L*` and `VK_R*` keys.
+    pub fn resync(&mut self, is_down: impl Fn(u16) -> bool) {
+        *self = Self {
+            left_shift: is_down(VK_LSHIFT),
+            right_shift: is_down(VK_RSHIFT),
+            left_ctrl: is_down(VK_LCONTROL),
+            right_ctrl: is_down(VK_RCONTROL),
+            left_alt: is_down(VK_LMENU),
+            right_alt: is_down(VK_RMENU),
+        };
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        let ctrl = self.left_ctrl || self.right_ctrl;
+
+        Modifiers {
+            alt: self.left_alt || self.right_alt,
+            shift: self.left_shift || self.right_shift,
+            mac_cmd: false,
+            command: ctrl,
+            ctrl,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Number of variants in [`egui::Key`], bump it along with a new arm of [`expected_vk`].
+    const KEY_VARIANTS: usize = 73;
+
+    /// Virtual-key code expected to produce `key`, on the main part of the keyboard.
+    /// Match is exhaustive, so a new variant in egui fails to build until it's mapped.
+    fn expected_vk(key: Key) -> u16 {
+        match key {
+            Key::ArrowDown => VK_DOWN,
+            Key::ArrowLeft => VK_LEFT,
+            Key::ArrowRight => VK_RIGHT,
+            Key::ArrowUp => VK_UP,
+            Key::Escape => VK_ESCAPE,
+            Key::Tab => VK_TAB,
+            Key::Backspace => VK_BACK,
+            Key::Enter => VK_RETURN,
+            Key::Space => VK_SPACE,
+            Key::Insert => VK_INSERT,
+            Key::Delete => VK_DELETE,
+            Key::Home => VK_HOME,
+            Key::End => VK_END,
+            Key::PageUp => VK_PRIOR,
+            Key::PageDown => VK_NEXT,
+            Key::Minus => VK_OEM_MINUS,
+            Key::PlusEquals => VK_OEM_PLUS,
+            Key::Num0 => 0x30,
+            Key::Num1 => 0x31,
+            Key::Num2 => 0x32,
+            Key::Num3 => 0x33,
+            Key::Num4 => 0x34,
+            Key::Num5 => 0x35,
+            Key::Num6 => 0x36,
+            Key::Num7 => 0x37,
+            Key::Num8 => 0x38,
+            Key::Num9 => 0x39,
+            Key::A => 0x41,
+            Key::B => 0x42,
+            Key::C => 0x43,
+            Key::D => 0x44,
+            Key::E => 0x45,
+            Key::F => 0x46,
+            Key::G => 0x47,
+            Key::H => 0x48,
+            Key::I => 0x49,
+            Key::J => 0x4A,
+            Key::K => 0x4B,
+            Key::L => 0x4C,
+            Key::M => 0x4D,
+            Key::N => 0x4E,
+            Key::O => 0x4F,
+            Key::P => 0x50,
+            Key::Q => 0x51,
+            Key::R => 0x52,
+            Key::S => 0x53,
+            Key::T => 0x54,
+            Key::U => 0x55,
+            Key::V => 0x56,
+            Key::W => 0x57,
+            Key::X => 0x58,
+            Key::Y => 0x59,
+            Key::Z => 0x5A,
+            Key::F1 => VK_F1,
+            Key::F2 => 0x71,
+            Key::F3 => 0x72,
+            Key::F4 => 0x73,
+            Key::F5 => 0x74,
+            Key::F6 => 0x75,
+            Key::F7 => 0x76,
+            Key::F8 => 0x77,
+            Key::F9 => 0x78,
+            Key::F10 => 0x79,
+            Key::F11 => 0x7A,
+            Key::F12 => 0x7B,
+            Key::F13 => 0x7C,
+            Key::F14 => 0x7D,
+            Key::F15 => 0x7E,
+            Key::F16 => 0x7F,
+            Key::F17 => 0x80,
+            Key::F18 => 0x81,
+            Key::F19 => 0x82,
+            Key::F20 => VK_F20,
+        }
+    }
+
+    /// Lparam of a key from the extended part of the keyboard.
+    const EXTENDED: isize = EXTENDED_KEY_FLAG;
+
+    #[test]
+    fn every_key_is_reachable() {
+        let mut reached = vec![];
+        for vk in 0..=0xFFusize {
+            for lparam in [0, EXTENDED] {
+                if let Some(key) = translate_key(vk, lparam) {
+                    if !reached.contains(&key) {
+                        reached.push(key);
+                    }
+                }
+            }
+        }
+
+        assert_eq!(reached.len(), KEY_VARIANTS);
+        for key in reached {
+            let vk = expected_vk(key);
+            // Navigation keys are extended on the main part of the keyboard.
+            let lparam = if is_numpad_key(vk, 0) { EXTENDED } else { 0 };
+
+            assert!(!is_numpad_key(vk, lparam), "{:?}", key);
+            assert_eq!(translate_key(vk as _, lparam), Some(key), "{:?}", key);
+        }
+    }
+
+    #[test]
+    fn navigation_block_is_not_numpad() {
+        for vk in [
+            VK_INSERT, VK_DELETE, VK_HOME, VK_END, VK_PRIOR, VK_NEXT, VK_DOWN, VK_LEFT, VK_RIGHT,
+            VK_UP,
+        ] {
+            assert!(!is_numpad_key(vk, EXTENDED), "{:#x}", vk);
+            assert!(is_numpad_key(vk, 0), "{:#x}", vk);
+        }
+    }
+
+    #[test]
+    fn keypad_is_told_apart_by_extended_flag() {
+        // Keypad's Enter is extended, the main one is not.
+        assert!(is_numpad_key(VK_RETURN, EXTENDED));
+        assert!(!is_numpad_key(VK_RETURN, 0));
+        assert_eq!(translate_key(VK_RETURN as _, EXTENDED), Some(Key::Enter));
+        assert_eq!(translate_key(VK_RETURN as _, 0), Some(Key::Enter));
+
+        // Without NumLock keypad sends the same codes as the navigation block.
+        assert_eq!(translate_key(VK_HOME as _, 0), Some(Key::Home));
+        assert_eq!(translate_key(VK_HOME as _, EXTENDED), Some(Key::Home));
+        assert_eq!(translate_key(VK_LEFT as _, 0), Some(Key::ArrowLeft));
+        assert_eq!(translate_key(VK_LEFT as _, EXTENDED), Some(Key::ArrowLeft));
+    }
+
+    #[test]
+    fn keypad_digits_and_operators() {
+        for (i, key) in [
+            Key::Num0,
+            Key::Num1,
+            Key::Num2,
+            Key::Num3,
+            Key::Num4,
+            Key::Num5,
+            Key::Num6,
+            Key::Num7,
+            Key::Num8,
+            Key::Num9,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let vk = VK_NUMPAD0 + i as u16;
+            assert!(is_numpad_key(vk, 0));
+            assert_eq!(translate_key(vk as _, 0), Some(key));
+        }
+
+        assert_eq!(translate_key(VK_ADD as _, 0), Some(Key::PlusEquals));
+        assert_eq!(translate_key(VK_SUBTRACT as _, 0), Some(Key::Minus));
+        assert!(!is_numpad_key(VK_OEM_PLUS, 0));
+        assert!(!is_numpad_key(0x35, 0));
+    }
+}
@@ -9,16 +9,6 @@ macro_rules! expect {
     };
 }
 
-macro_rules! panic_msg {
-    ($($t:tt)*) => {
-        if cfg!(feature = "no-msgs") {
-            unimplemented!()
-        } else {
-            panic!($($t)*)
-        }
-    };
-}
-
 /// Creates zero terminated string.
 macro_rules! pc_str {
     ($cstr:expr) => {
@@ -30,9 +20,37 @@ mod app;
 pub use app::*;
 
 mod backup;
+mod buffers;
+mod callback;
+mod capture;
+mod clip;
+mod clipboard;
+mod color;
+mod cursor;
+mod decode;
+mod device;
+mod error;
+mod ime;
 mod input;
+mod keymap;
+mod mapping;
 mod mesh;
+mod mip;
+mod pipeline;
+mod sampler;
 mod shader;
 mod texture;
+mod url;
 
-pub use input::InputResult;
+#[cfg(windows)]
+pub use crate::clipboard::WindowsClipboard;
+pub use crate::clipboard::{ClipboardBackend, MemoryClipboard};
+pub use callback::{CallbackInfo, D3D11CallbackFn};
+pub use capture::{CaptureMode, WndProcResult};
+pub use color::ColorMode;
+pub use error::{Error, Result};
+pub use input::{Hotkey, InputResult};
+pub use mapping::CoordinateMapping;
+pub use mip::MipMode;
+pub use texture::{TextureMemoryPolicy, TextureMemoryStats, TextureMemoryUsage};
+pub use url::{is_web_url, NoopUrlOpener, RecordingUrlOpener, ShellUrlOpener, UrlOpener};
@@ -0,0 +1,40 @@
+use egui::{Pos2, Vec2};
+
+/// Maps mouse positions from the window's client area onto the backbuffer.
+/// Needed when the game renders at a different resolution than its window.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum CoordinateMapping {
+    /// Client coordinates are used as backbuffer coordinates.
+    Identity,
+    /// Client area is stretched over the whole backbuffer.
+    #[default]
+    Stretch,
+    /// Arbitrary mapping, arguments are position, client size and backbuffer size, in pixels.
+    /// Has no inverse, so IME windows are placed at unmapped text cursor position.
+    Custom(fn(Pos2, Vec2, Vec2) -> Pos2),
+}
+
+impl CoordinateMapping {
+    /// Converts client position into backbuffer position.
+    pub fn to_backbuffer(self, pos: Pos2, client: Vec2, backbuffer: Vec2) -> Pos2 {
+        match self {
+            Self::Stretch if client.x > 0. && client.y > 0. => Pos2::new(
+                pos.x * backbuffer.x / client.x,
+                pos.y * backbuffer.y / client.y,
+            ),
+            Self::Custom(map) => map(pos, client, backbuffer),
+            _ => pos,
+        }
+    }
+
+    /// Converts backbuffer position into client position.
+    pub fn to_client(self, pos: Pos2, client: Vec2, backbuffer: Vec2) -> Pos2 {
+        match self {
+            Self::Stretch if backbuffer.x > 0. && backbuffer.y > 0. => Pos2::new(
+                pos.x * client.x / backbuffer.x,
+                pos.y * client.y / backbuffer.y,
+            ),
+            _ => pos,
+        }
+    }
+}
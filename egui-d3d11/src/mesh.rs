@@ -1,9 +1,5 @@
-use egui::{epaint::Vertex, Mesh, Pos2, Rect, Rgba, TextureId};
-use std::mem::size_of;
-use windows::Win32::Graphics::Direct3D11::{
-    ID3D11Buffer, ID3D11Device, D3D11_BIND_INDEX_BUFFER, D3D11_BIND_VERTEX_BUFFER,
-    D3D11_BUFFER_DESC, D3D11_SUBRESOURCE_DATA, D3D11_USAGE_DEFAULT,
-};
+use crate::color::ColorMode;
+use egui::{epaint::Vertex, Mesh, PaintCallback, Pos2, Rect, Rgba, TextureId};
 
 pub struct GpuMesh {
     pub indices: Vec<u32>,
@@ -13,7 +9,14 @@ pub struct GpuMesh {
 }
 
 impl GpuMesh {
-    pub fn from_mesh((w, h): (f32, f32), mesh: Mesh, scissors: Rect) -> Option<Self> {
+    /// `(w, h)` is the screen size in pixels, `mesh` and `scissors` are in points.
+    pub fn from_mesh(
+        (w, h): (f32, f32),
+        pixels_per_point: f32,
+        mesh: Mesh,
+        scissors: Rect,
+        color_mode: ColorMode,
+    ) -> Option<Self> {
         if mesh.indices.is_empty() || mesh.indices.len() % 3 != 0 {
             None
         } else {
@@ -22,11 +25,11 @@ impl GpuMesh {
                 .into_iter()
                 .map(|v| GpuVertex {
                     pos: Pos2::new(
-                        (v.pos.x - w / 2.) / (w / 2.),
-                        (v.pos.y - h / 2.) / -(h / 2.),
+                        (v.pos.x * pixels_per_point - w / 2.) / (w / 2.),
+                        (v.pos.y * pixels_per_point - h / 2.) / -(h / 2.),
                     ),
                     uv: v.uv,
-                    color: v.color.into(),
+                    color: color_mode.vertex_color(v.color),
                 })
                 .collect();
 
@@ -40,6 +43,13 @@ impl GpuMesh {
     }
 }
 
+/// Single step of drawing a frame, in the order egui painted it.
+pub enum DrawCommand {
+    /// Index into the meshes uploaded for this frame.
+    Mesh(usize),
+    Callback(Rect, PaintCallback),
+}
+
 #[repr(C)]
 pub struct GpuVertex {
     pos: Pos2,
@@ -56,45 +66,3 @@ impl From<Vertex> for GpuVertex {
         }
     }
 }
-
-pub fn create_vertex_buffer(device: &ID3D11Device, mesh: &GpuMesh) -> ID3D11Buffer {
-    let desc = D3D11_BUFFER_DESC {
-        ByteWidth: (mesh.vertices.len() * size_of::<GpuVertex>()) as u32,
-        Usage: D3D11_USAGE_DEFAULT,
-        BindFlags: D3D11_BIND_VERTEX_BUFFER.0,
-        ..Default::default()
-    };
-
-    let init = D3D11_SUBRESOURCE_DATA {
-        pSysMem: mesh.vertices.as_ptr() as _,
-        ..Default::default()
-    };
-
-    unsafe {
-        expect!(
-            device.CreateBuffer(&desc, &init),
-            "Failed to create vertex buffer"
-        )
-    }
-}
-
-pub fn create_index_buffer(device: &ID3D11Device, mesh: &GpuMesh) -> ID3D11Buffer {
-    let desc = D3D11_BUFFER_DESC {
-        ByteWidth: (mesh.indices.len() * size_of::<u32>()) as u32,
-        Usage: D3D11_USAGE_DEFAULT,
-        BindFlags: D3D11_BIND_INDEX_BUFFER.0,
-        ..Default::default()
-    };
-
-    let init = D3D11_SUBRESOURCE_DATA {
-        pSysMem: mesh.indices.as_ptr() as _,
-        ..Default::default()
-    };
-
-    unsafe {
-        expect!(
-            device.CreateBuffer(&desc, &init),
-            "Failed to create index buffer"
-        )
-    }
-}
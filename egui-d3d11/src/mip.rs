@@ -0,0 +1,178 @@
+use egui::Color32;
+
+/// How mip levels of a texture are made.
+/// Worth enabling for big images drawn scaled down, they alias badly without mips.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MipMode {
+    /// Single level, what egui's own textures use.
+    #[default]
+    Off,
+    /// Levels are generated by the device with `GenerateMips`.
+    Gpu,
+    /// Levels are box filtered on the CPU and uploaded with the image.
+    /// For devices which can't render into the texture's format.
+    Cpu,
+}
+
+/// One level of a mip chain.
+pub struct MipLevel {
+    pub pixels: Vec<Color32>,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Number of levels in a full chain, down to 1x1.
+pub fn level_count(width: usize, height: usize) -> u32 {
+    usize::BITS - width.max(height).max(1).leading_zeros()
+}
+
+/// Halves the image, every pixel is the average of up to 2x2 source pixels.
+/// Last row or column of odd sizes is dropped,
+/// 1 pixel wide or tall images only shrink along the other axis.
+pub fn downsample(pixels: &[Color32], width: usize, height: usize) -> MipLevel {
+    let (w, h) = ((width / 2).max(1), (height / 2).max(1));
+    let mut out = Vec::with_capacity(w * h);
+
+    for y in 0..h {
+        let (y0, y1) = (y * 2, (y * 2 + 1).min(height - 1));
+        for x in 0..w {
+            let (x0, x1) = (x * 2, (x * 2 + 1).min(width - 1));
+            let texels = [
+                pixels[y0 * width + x0],
+                pixels[y0 * width + x1],
+                pixels[y1 * width + x0],
+                pixels[y1 * width + x1],
+            ];
+
+            let mut sum = [0u32; 4];
+            for texel in texels {
+                for (s, c) in sum.iter_mut().zip(texel.to_array()) {
+                    *s += c as u32;
+                }
+            }
+
+            let [r, g, b, a] = sum.map(|s| ((s + 2) / 4) as u8);
+            out.push(Color32::from_rgba_premultiplied(r, g, b, a));
+        }
+    }
+
+    MipLevel {
+        pixels: out,
+        width: w,
+        height: h,
+    }
+}
+
+/// Every level after the base one, smallest is 1x1.
+pub fn mip_chain(pixels: &[Color32], width: usize, height: usize) -> Vec<MipLevel> {
+    let mut levels: Vec<MipLevel> = vec![];
+
+    while level_count(width, height) as usize > levels.len() + 1 {
+        let next = match levels.last() {
+            Some(prev) => downsample(&prev.pixels, prev.width, prev.height),
+            None => downsample(pixels, width, height),
+        };
+        levels.push(next);
+    }
+
+    levels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray(pixels: &[u8]) -> Vec<Color32> {
+        pixels
+            .iter()
+            .map(|&l| Color32::from_rgba_premultiplied(l, l, l, l))
+            .collect()
+    }
+
+    #[test]
+    fn level_counts() {
+        assert_eq!(level_count(1, 1), 1);
+        assert_eq!(level_count(0, 0), 1);
+        assert_eq!(level_count(0, 1), 1);
+        assert_eq!(level_count(2, 2), 2);
+        assert_eq!(level_count(256, 256), 9);
+        assert_eq!(level_count(256, 1), 9);
+        assert_eq!(level_count(1, 256), 9);
+        // Non power of two sizes round down, 300 -> 150 -> 75 -> 37 -> 18 -> 9 -> 4 -> 2 -> 1.
+        assert_eq!(level_count(300, 200), 9);
+        assert_eq!(level_count(3, 3), 2);
+        assert_eq!(level_count(255, 1), 8);
+    }
+
+    #[test]
+    fn downsample_averages_rounding_to_nearest() {
+        #[rustfmt::skip]
+        let pixels = gray(&[
+            0, 4, 10, 10,
+            4, 8, 10, 11,
+        ]);
+        let level = downsample(&pixels, 4, 2);
+
+        assert_eq!((level.width, level.height), (2, 1));
+        // (0 + 4 + 4 + 8) / 4 = 4, (10 + 10 + 10 + 11) / 4 = 10.25.
+        assert_eq!(level.pixels, gray(&[4, 10]));
+
+        let level = downsample(&gray(&[0, 1, 1, 0]), 2, 2);
+        // 0.5 rounds up.
+        assert_eq!(level.pixels, gray(&[1]));
+    }
+
+    #[test]
+    fn downsample_drops_last_row_and_column_of_odd_sizes() {
+        #[rustfmt::skip]
+        let pixels = gray(&[
+            8, 8, 200,
+            8, 8, 200,
+            200, 200, 200,
+        ]);
+        let level = downsample(&pixels, 3, 3);
+
+        assert_eq!((level.width, level.height), (1, 1));
+        assert_eq!(level.pixels, gray(&[8]));
+    }
+
+    #[test]
+    fn downsample_one_pixel_wide_and_tall() {
+        let level = downsample(&gray(&[0, 8, 16, 24]), 1, 4);
+        assert_eq!((level.width, level.height), (1, 2));
+        assert_eq!(level.pixels, gray(&[4, 20]));
+
+        let level = downsample(&gray(&[0, 8, 16, 24]), 4, 1);
+        assert_eq!((level.width, level.height), (2, 1));
+        assert_eq!(level.pixels, gray(&[4, 20]));
+
+        let level = downsample(&gray(&[100]), 1, 1);
+        assert_eq!((level.width, level.height), (1, 1));
+        assert_eq!(level.pixels, gray(&[100]));
+    }
+
+    #[test]
+    fn chain_ends_at_one_pixel() {
+        for (width, height) in [
+            (1, 1),
+            (2, 2),
+            (256, 256),
+            (300, 200),
+            (7, 1),
+            (1, 5),
+            (3, 3),
+        ] {
+            let pixels = vec![Color32::WHITE; width * height];
+            let chain = mip_chain(&pixels, width, height);
+
+            assert_eq!(chain.len() as u32, level_count(width, height) - 1);
+            for level in &chain {
+                assert_eq!(level.pixels.len(), level.width * level.height);
+                assert!(level.pixels.iter().all(|&p| p == Color32::WHITE));
+            }
+            if let Some(last) = chain.last() {
+                assert_eq!((last.width, last.height), (1, 1));
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use crate::error::{Error, Result};
+use windows::Win32::Graphics::Direct3D11::{
+    ID3D11BlendState, ID3D11DepthStencilState, ID3D11Device, ID3D11DeviceContext,
+    ID3D11RasterizerState, D3D11_BLEND_DESC, D3D11_BLEND_INV_DEST_ALPHA, D3D11_BLEND_INV_SRC_ALPHA,
+    D3D11_BLEND_ONE, D3D11_BLEND_OP_ADD, D3D11_COLOR_WRITE_ENABLE_ALL, D3D11_COMPARISON_ALWAYS,
+    D3D11_CULL_NONE, D3D11_DEPTH_STENCIL_DESC, D3D11_DEPTH_WRITE_MASK_ZERO, D3D11_FILL_SOLID,
+    D3D11_RASTERIZER_DESC, D3D11_RENDER_TARGET_BLEND_DESC,
+};
+
+/// Fixed function state used to draw egui.
+/// Created once per device, binding it doesn't allocate anything.
+/// Samplers depend on the texture, see [`crate::sampler::SamplerCache`].
+pub struct PipelineStates {
+    rasterizer: ID3D11RasterizerState,
+    blend: ID3D11BlendState,
+    depth_stencil: ID3D11DepthStencilState,
+}
+
+impl PipelineStates {
+    const BLEND_FACTOR: [f32; 4] = [0., 0., 0., 0.];
+
+    pub fn new(dev: &ID3D11Device) -> Result<Self> {
+        unsafe {
+            Ok(Self {
+                rasterizer: dev
+                    .CreateRasterizerState(&Self::rasterizer_desc())
+                    .map_err(|e| Error::State("Failed to create rasterizer state", e))?,
+                blend: dev
+                    .CreateBlendState(&Self::blend_desc())
+                    .map_err(|e| Error::State("Failed to create blend state", e))?,
+                depth_stencil: dev
+                    .CreateDepthStencilState(&Self::depth_stencil_desc())
+                    .map_err(|e| Error::State("Failed to create depth stencil state", e))?,
+            })
+        }
+    }
+
+    pub fn bind(&self, ctx: &ID3D11DeviceContext) {
+        unsafe {
+            ctx.RSSetState(&self.rasterizer);
+            ctx.OMSetBlendState(&self.blend, Self::BLEND_FACTOR.as_ptr(), 0xffffffff);
+            ctx.OMSetDepthStencilState(&self.depth_stencil, 0);
+        }
+    }
+
+    fn rasterizer_desc() -> D3D11_RASTERIZER_DESC {
+        D3D11_RASTERIZER_DESC {
+            FillMode: D3D11_FILL_SOLID,
+            CullMode: D3D11_CULL_NONE,
+            FrontCounterClockwise: false.into(),
+            DepthBias: false.into(),
+            DepthBiasClamp: 0.,
+            SlopeScaledDepthBias: 0.,
+            DepthClipEnable: false.into(),
+            ScissorEnable: true.into(),
+            MultisampleEnable: false.into(),
+            AntialiasedLineEnable: false.into(),
+        }
+    }
+
+    /// egui outputs colors with premultiplied alpha.
+    fn blend_desc() -> D3D11_BLEND_DESC {
+        let mut targets: [D3D11_RENDER_TARGET_BLEND_DESC; 8] = Default::default();
+        targets[0].BlendEnable = true.into();
+        targets[0].SrcBlend = D3D11_BLEND_ONE;
+        targets[0].DestBlend = D3D11_BLEND_INV_SRC_ALPHA;
+        targets[0].BlendOp = D3D11_BLEND_OP_ADD;
+        targets[0].SrcBlendAlpha = D3D11_BLEND_INV_DEST_ALPHA;
+        targets[0].DestBlendAlpha = D3D11_BLEND_ONE;
+        targets[0].BlendOpAlpha = D3D11_BLEND_OP_ADD;
+        targets[0].RenderTargetWriteMask = D3D11_COLOR_WRITE_ENABLE_ALL.0 as _;
+
+        D3D11_BLEND_DESC {
+            AlphaToCoverageEnable: false.into(),
+            IndependentBlendEnable: false.into(),
+            RenderTarget: targets,
+        }
+    }
+
+    fn depth_stencil_desc() -> D3D11_DEPTH_STENCIL_DESC {
+        D3D11_DEPTH_STENCIL_DESC {
+            DepthEnable: false.into(),
+            DepthWriteMask: D3D11_DEPTH_WRITE_MASK_ZERO,
+            DepthFunc: D3D11_COMPARISON_ALWAYS,
+            StencilEnable: false.into(),
+            ..Default::default()
+        }
+    }
+}
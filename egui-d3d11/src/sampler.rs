@@ -0,0 +1,83 @@
+use crate::error::{Error, Result};
+use egui::TextureFilter;
+use windows::Win32::Graphics::Direct3D11::{
+    ID3D11Device, ID3D11DeviceContext, ID3D11SamplerState, D3D11_COMPARISON_ALWAYS, D3D11_FILTER,
+    D3D11_FILTER_MIN_MAG_MIP_LINEAR, D3D11_FILTER_MIN_MAG_MIP_POINT, D3D11_SAMPLER_DESC,
+    D3D11_TEXTURE_ADDRESS_BORDER, D3D11_TEXTURE_ADDRESS_MODE,
+};
+
+/// Everything a sampler is created from.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SamplerKey {
+    pub filter: TextureFilter,
+    pub address: D3D11_TEXTURE_ADDRESS_MODE,
+}
+
+impl SamplerKey {
+    /// Sampler for textures drawn by egui, they are never repeated.
+    pub fn new(filter: TextureFilter) -> Self {
+        Self {
+            filter,
+            address: D3D11_TEXTURE_ADDRESS_BORDER,
+        }
+    }
+
+    fn d3d11_filter(self) -> D3D11_FILTER {
+        match self.filter {
+            TextureFilter::Nearest => D3D11_FILTER_MIN_MAG_MIP_POINT,
+            TextureFilter::Linear => D3D11_FILTER_MIN_MAG_MIP_LINEAR,
+        }
+    }
+
+    fn desc(self) -> D3D11_SAMPLER_DESC {
+        D3D11_SAMPLER_DESC {
+            Filter: self.d3d11_filter(),
+            AddressU: self.address,
+            AddressV: self.address,
+            AddressW: self.address,
+            MipLODBias: 0.,
+            ComparisonFunc: D3D11_COMPARISON_ALWAYS,
+            MinLOD: 0.,
+            MaxLOD: f32::MAX,
+            BorderColor: [1., 1., 1., 1.],
+            ..Default::default()
+        }
+    }
+}
+
+/// Samplers are created on first use and kept for the lifetime of the device.
+/// There are only a handful of combinations, so a flat list is enough.
+#[derive(Default)]
+pub struct SamplerCache {
+    samplers: Vec<(SamplerKey, ID3D11SamplerState)>,
+}
+
+impl SamplerCache {
+    pub fn get(&mut self, dev: &ID3D11Device, key: SamplerKey) -> Result<ID3D11SamplerState> {
+        if let Some((_, sampler)) = self.samplers.iter().find(|(k, _)| *k == key) {
+            return Ok(sampler.clone());
+        }
+
+        let sampler = unsafe {
+            dev.CreateSamplerState(&key.desc())
+                .map_err(|e| Error::State("Failed to create sampler", e))?
+        };
+
+        self.samplers.push((key, sampler.clone()));
+        Ok(sampler)
+    }
+
+    /// Binds sampler for `key` to the first pixel shader slot.
+    pub fn bind(
+        &mut self,
+        dev: &ID3D11Device,
+        ctx: &ID3D11DeviceContext,
+        key: SamplerKey,
+    ) -> Result<()> {
+        let sampler = self.get(dev, key)?;
+        unsafe {
+            ctx.PSSetSamplers(0, &[Some(sampler)]);
+        }
+        Ok(())
+    }
+}
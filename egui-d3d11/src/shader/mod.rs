@@ -1,3 +1,4 @@
+use crate::error::{Error, Result};
 use std::{io::Write, slice::from_raw_parts};
 
 use windows::{
@@ -11,35 +12,21 @@ use windows::{
     },
 };
 
-trait Shader {
+trait Shader: Sized {
     const ENTRY: PCSTR;
     const TARGET: PCSTR;
 
-    unsafe fn create_shader(device: &ID3D11Device, blob: &ShaderData) -> Self;
+    unsafe fn create_shader(device: &ID3D11Device, blob: &ShaderData) -> Result<Self>;
 }
 
 impl Shader for ID3D11VertexShader {
     const ENTRY: PCSTR = pc_str!("vs_main");
     const TARGET: PCSTR = pc_str!("vs_5_0");
 
-    unsafe fn create_shader(device: &ID3D11Device, blob: &ShaderData) -> Self {
-        match blob {
-            ShaderData::EmbeddedData(arr) => {
-                expect!(
-                    device.CreateVertexShader(*arr, None),
-                    "Failed to create vertex shader"
-                )
-            }
-            ShaderData::CompiledBlob(blob) => {
-                expect!(
-                    device.CreateVertexShader(
-                        from_raw_parts(blob.GetBufferPointer() as _, blob.GetBufferSize()),
-                        None
-                    ),
-                    "Failed to create vertex shader"
-                )
-            }
-        }
+    unsafe fn create_shader(device: &ID3D11Device, blob: &ShaderData) -> Result<Self> {
+        device
+            .CreateVertexShader(blob.bytes(), None)
+            .map_err(|e| Error::Shader("Failed to create vertex shader", e))
     }
 }
 
@@ -47,24 +34,10 @@ impl Shader for ID3D11PixelShader {
     const ENTRY: PCSTR = pc_str!("ps_main");
     const TARGET: PCSTR = pc_str!("ps_5_0");
 
-    unsafe fn create_shader(device: &ID3D11Device, blob: &ShaderData) -> Self {
-        match blob {
-            ShaderData::EmbeddedData(arr) => {
-                expect!(
-                    device.CreatePixelShader(*arr, None),
-                    "Failed to create vertex shader"
-                )
-            }
-            ShaderData::CompiledBlob(blob) => {
-                expect!(
-                    device.CreatePixelShader(
-                        from_raw_parts(blob.GetBufferPointer() as _, blob.GetBufferSize()),
-                        None
-                    ),
-                    "Failed to create vertex shader"
-                )
-            }
-        }
+    unsafe fn create_shader(device: &ID3D11Device, blob: &ShaderData) -> Result<Self> {
+        device
+            .CreatePixelShader(blob.bytes(), None)
+            .map_err(|e| Error::Shader("Failed to create pixel shader", e))
     }
 }
 
@@ -73,6 +46,17 @@ pub enum ShaderData {
     CompiledBlob(ID3DBlob),
 }
 
+impl ShaderData {
+    fn bytes(&self) -> &[u8] {
+        match self {
+            ShaderData::EmbeddedData(arr) => arr,
+            ShaderData::CompiledBlob(blob) => unsafe {
+                from_raw_parts(blob.GetBufferPointer() as _, blob.GetBufferSize())
+            },
+        }
+    }
+}
+
 pub struct CompiledShaders {
     pub vertex: ID3D11VertexShader,
     pub pixel: ID3D11PixelShader,
@@ -80,10 +64,10 @@ pub struct CompiledShaders {
 }
 
 impl CompiledShaders {
-    pub fn new(device: &ID3D11Device) -> Self {
+    pub fn new(device: &ID3D11Device) -> Result<Self> {
         if cfg!(feature = "force-compile") {
-            let (vcache, vertex) = Self::compile_shader::<ID3D11VertexShader>(device);
-            let (_pcache, pixel) = Self::compile_shader::<ID3D11PixelShader>(device);
+            let (vcache, vertex) = Self::compile_shader::<ID3D11VertexShader>(device)?;
+            let (_pcache, pixel) = Self::compile_shader::<ID3D11PixelShader>(device)?;
 
             if cfg!(feature = "save-blob") {
                 unsafe {
@@ -113,39 +97,34 @@ impl CompiledShaders {
                 }
             }
 
-            Self {
+            Ok(Self {
                 vertex,
                 pixel,
                 cache: ShaderData::CompiledBlob(vcache),
-            }
+            })
         } else {
             unsafe {
                 let cache = ShaderData::EmbeddedData(include_bytes!("vertex.bin"));
-                let vertex = ID3D11VertexShader::create_shader(device, &cache);
+                let vertex = ID3D11VertexShader::create_shader(device, &cache)?;
                 let pixel = ID3D11PixelShader::create_shader(
                     device,
                     &ShaderData::EmbeddedData(include_bytes!("pixel.bin")),
-                );
+                )?;
 
-                Self {
+                Ok(Self {
                     cache,
                     vertex,
                     pixel,
-                }
+                })
             }
         }
     }
 
     pub fn bytecode(&self) -> &[u8] {
-        match &self.cache {
-            ShaderData::EmbeddedData(arr) => *arr,
-            ShaderData::CompiledBlob(blob) => unsafe {
-                from_raw_parts(blob.GetBufferPointer() as _, blob.GetBufferSize())
-            },
-        }
+        self.cache.bytes()
     }
 
-    fn compile_shader<S: Shader>(device: &ID3D11Device) -> (ID3DBlob, S) {
+    fn compile_shader<S: Shader>(device: &ID3D11Device) -> Result<(ID3DBlob, S)> {
         const SHADER_TEXT: &str = include_str!("shader.hlsl");
 
         let mut flags = D3DCOMPILE_ENABLE_STRICTNESS;
@@ -172,22 +151,22 @@ impl CompiledShaders {
             )
             .is_err()
             {
-                if !cfg!(feature = "no-msgs") {
-                    panic!(
-                        "{}",
-                        std::str::from_utf8_unchecked(std::slice::from_raw_parts(
-                            error.as_ref().unwrap().GetBufferPointer() as *const u8,
-                            error.as_ref().unwrap().GetBufferSize(),
+                let message = match &error {
+                    Some(error) if !cfg!(feature = "no-msgs") => {
+                        String::from_utf8_lossy(std::slice::from_raw_parts(
+                            error.GetBufferPointer() as *const u8,
+                            error.GetBufferSize(),
                         ))
-                    );
-                } else {
-                    panic!();
-                }
+                        .into_owned()
+                    }
+                    _ => String::new(),
+                };
+
+                Err(Error::ShaderCompile(message))
             } else {
-                (
-                    code.clone().unwrap(),
-                    S::create_shader(device, &ShaderData::CompiledBlob(code.unwrap())),
-                )
+                let code = expect!(code, "Compiler returned no bytecode");
+                let shader = S::create_shader(device, &ShaderData::CompiledBlob(code.clone()))?;
+                Ok((code, shader))
             }
         }
     }
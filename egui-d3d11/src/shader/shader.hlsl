@@ -22,6 +22,8 @@ vs_out vs_main(vs_in input) {
 sampler sampler0;
 Texture2D texture0;
 
+// Colors are premultiplied and already encoded for the render target,
+// see `ColorMode` for details.
 float4 ps_main(vs_out input) : SV_TARGET {
-  return pow(input.color, 1.0 / 2.2) * texture0.Sample(sampler0, input.uv);
+  return input.color * texture0.Sample(sampler0, input.uv);
 }
\ No newline at end of file
@@ -1,155 +1,491 @@
-use egui::{Color32, ImageData, TextureId, TexturesDelta};
-use std::{collections::HashMap, mem::size_of, slice::from_raw_parts_mut};
+use crate::{
+    color::ColorMode,
+    error::{Error, Result},
+    mip::{level_count, mip_chain, MipMode},
+};
+use egui::{Color32, ImageData, TextureFilter, TextureId, TexturesDelta};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    mem::size_of,
+    ptr::null,
+};
 use windows::Win32::Graphics::{
     Direct3D::D3D11_SRV_DIMENSION_TEXTURE2D,
     Direct3D11::{
         ID3D11Device, ID3D11DeviceContext, ID3D11ShaderResourceView, ID3D11Texture2D,
-        D3D11_BIND_SHADER_RESOURCE, D3D11_CPU_ACCESS_WRITE, D3D11_MAP_WRITE_DISCARD,
-        D3D11_SHADER_RESOURCE_VIEW_DESC, D3D11_SHADER_RESOURCE_VIEW_DESC_0, D3D11_SUBRESOURCE_DATA,
-        D3D11_TEX2D_SRV, D3D11_TEXTURE2D_DESC, D3D11_USAGE_DYNAMIC,
+        D3D11_BIND_RENDER_TARGET, D3D11_BIND_SHADER_RESOURCE, D3D11_BOX,
+        D3D11_RESOURCE_MISC_GENERATE_MIPS, D3D11_SHADER_RESOURCE_VIEW_DESC,
+        D3D11_SHADER_RESOURCE_VIEW_DESC_0, D3D11_SUBRESOURCE_DATA, D3D11_TEX2D_SRV,
+        D3D11_TEXTURE2D_DESC, D3D11_USAGE_DEFAULT,
     },
-    Dxgi::Common::{DXGI_FORMAT_R8G8B8A8_UNORM, DXGI_SAMPLE_DESC},
+    Dxgi::Common::{DXGI_FORMAT, DXGI_SAMPLE_DESC},
 };
 
-struct ManagedTexture {
+/// Font atlas, egui allocates it first and only updates it afterwards.
+const FONT_ATLAS: TextureId = TextureId::Managed(0);
+
+/// Texture owned by the device, dropped when the device is lost.
+struct GpuTexture {
     resource: ID3D11ShaderResourceView,
     texture: ID3D11Texture2D,
-    pixels: Vec<Color32>,
+    mip: MipMode,
+}
+
+struct ManagedTexture {
+    gpu: Option<GpuTexture>,
+    /// CPU copy, see [`TextureMemoryPolicy`].
+    pixels: Option<Vec<Color32>>,
     width: usize,
+    height: usize,
+    filter: TextureFilter,
+    /// Policy in effect when the texture was allocated.
+    policy: TextureMemoryPolicy,
+}
+
+/// What is kept of egui's textures besides their device copy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextureMemoryPolicy {
+    /// Pixels are mirrored on the CPU, so textures survive device loss and mip mode changes.
+    #[default]
+    KeepCpuCopy,
+    /// Only the device copy exists. Textures can't survive a rebuild, they are dropped along with
+    /// the device, or when the backbuffer's color mode changes, and egui won't send them again.
+    /// Mip mode changes don't apply to already allocated textures.
+    /// Font atlas always keeps its pixels, so text survives.
+    /// Textures using [`MipMode::Cpu`] keep their pixels anyway, mips are built from them.
+    GpuOnly,
+}
+
+/// Memory used by a group of egui's textures.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextureMemoryUsage {
+    /// Textures with a CPU copy of their pixels.
+    pub cpu_textures: usize,
+    /// Bytes taken by CPU copies.
+    pub cpu_bytes: usize,
+    /// Textures living on the device.
+    pub gpu_textures: usize,
+    /// Estimated bytes taken on the device, mip levels included.
+    pub gpu_bytes: usize,
+}
+
+/// Memory used by egui's textures, split by the [`TextureMemoryPolicy`] each texture was allocated under.
+/// Textures allocated under [`TextureMemoryPolicy::GpuOnly`] can still have a CPU copy, see its docs.
+/// Native ones are owned by the game and aren't counted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextureMemoryStats {
+    pub keep_cpu_copy: TextureMemoryUsage,
+    pub gpu_only: TextureMemoryUsage,
+}
+
+impl TextureMemoryStats {
+    /// Usage of textures allocated under `policy`.
+    pub fn policy(&self, policy: TextureMemoryPolicy) -> TextureMemoryUsage {
+        match policy {
+            TextureMemoryPolicy::KeepCpuCopy => self.keep_cpu_copy,
+            TextureMemoryPolicy::GpuOnly => self.gpu_only,
+        }
+    }
+
+    /// Usage of all textures.
+    pub fn total(&self) -> TextureMemoryUsage {
+        let (a, b) = (self.keep_cpu_copy, self.gpu_only);
+        TextureMemoryUsage {
+            cpu_textures: a.cpu_textures + b.cpu_textures,
+            cpu_bytes: a.cpu_bytes + b.cpu_bytes,
+            gpu_textures: a.gpu_textures + b.gpu_textures,
+            gpu_bytes: a.gpu_bytes + b.gpu_bytes,
+        }
+    }
 }
 
-#[derive(Default)]
 pub struct TextureAllocator {
     allocated: HashMap<TextureId, ManagedTexture>,
+    policy: TextureMemoryPolicy,
+    mip_modes: HashMap<TextureId, MipMode>,
+    /// Textures dropped by [`Self::release`] that egui still thinks are alive.
+    lost: HashSet<TextureId>,
+    format: DXGI_FORMAT,
 }
 
 impl TextureAllocator {
+    pub fn new(color_mode: ColorMode) -> Self {
+        Self {
+            allocated: HashMap::new(),
+            policy: TextureMemoryPolicy::default(),
+            mip_modes: HashMap::new(),
+            lost: HashSet::new(),
+            format: color_mode.texture_format(),
+        }
+    }
+
     pub fn process_deltas(
         &mut self,
         dev: &ID3D11Device,
         ctx: &ID3D11DeviceContext,
         delta: TexturesDelta,
-    ) {
+    ) -> Result<()> {
+        // Remaining deltas are still applied, only the first failure is reported.
+        let mut result = Ok(());
+
         for (tid, delta) in delta.set {
-            if delta.is_whole() {
-                self.allocate_new(dev, tid, delta.image);
+            let applied = if delta.is_whole() {
+                self.allocate_new(dev, ctx, tid, delta.image, delta.filter)
             } else {
-                self.update_partial(ctx, tid, delta.image, delta.pos.unwrap());
+                self.update_partial(ctx, tid, delta.image, delta.filter, delta.pos.unwrap())
+            };
+
+            if result.is_ok() {
+                result = applied;
             }
         }
 
         for tid in delta.free {
             self.free(tid);
         }
+
+        result
     }
 
+    /// Native textures aren't managed here, see [`NativeTextures`].
     pub fn get_by_id(&self, tid: TextureId) -> Option<ID3D11ShaderResourceView> {
-        self.allocated.get(&tid).map(|t| t.resource.clone())
+        self.allocated
+            .get(&tid)
+            .and_then(|t| t.gpu.as_ref())
+            .map(|gpu| gpu.resource.clone())
+    }
+
+    /// Filter egui asked for when the texture was set.
+    pub fn get_filter(&self, tid: TextureId) -> Option<TextureFilter> {
+        self.allocated.get(&tid).map(|t| t.filter)
+    }
+
+    /// Drops every device object, pixels are kept for [`Self::restore`].
+    /// Textures without a CPU copy can't be restored, so they are gone.
+    pub fn release(&mut self) {
+        let lost = &mut self.lost;
+        self.allocated.retain(|&tid, tex| {
+            if tex.pixels.is_none() {
+                lost.insert(tid);
+            }
+            tex.pixels.is_some()
+        });
+
+        for tex in self.allocated.values_mut() {
+            tex.gpu = None;
+        }
+    }
+
+    /// Switches the format of egui's textures, those already allocated are uploaded again by [`Self::restore`].
+    pub fn set_color_mode(&mut self, color_mode: ColorMode) {
+        let format = color_mode.texture_format();
+        if format != self.format {
+            self.format = format;
+            self.release();
+        }
+    }
+
+    /// Applies to textures allocated afterwards.
+    pub fn set_memory_policy(&mut self, policy: TextureMemoryPolicy) {
+        self.policy = policy;
+    }
+
+    pub fn memory_stats(&self) -> TextureMemoryStats {
+        let mut stats = TextureMemoryStats::default();
+
+        for tex in self.allocated.values() {
+            let usage = match tex.policy {
+                TextureMemoryPolicy::KeepCpuCopy => &mut stats.keep_cpu_copy,
+                TextureMemoryPolicy::GpuOnly => &mut stats.gpu_only,
+            };
+
+            if let Some(pixels) = &tex.pixels {
+                usage.cpu_textures += 1;
+                usage.cpu_bytes += pixels.len() * size_of::<Color32>();
+            }
+
+            if let Some(gpu) = &tex.gpu {
+                let mut size = [tex.width, tex.height];
+                let levels = match gpu.mip {
+                    MipMode::Off => 1,
+                    _ => level_count(tex.width, tex.height),
+                };
+
+                usage.gpu_textures += 1;
+                for _ in 0..levels {
+                    usage.gpu_bytes += size[0] * size[1] * size_of::<Color32>();
+                    size = size.map(|s| (s / 2).max(1));
+                }
+            }
+        }
+
+        stats
+    }
+
+    /// Sets how mip levels of `tid` are made, it can be called before egui allocates the texture.
+    /// Already allocated texture is uploaded again by [`Self::restore`].
+    pub fn set_mip_mode(&mut self, tid: TextureId, mode: MipMode) {
+        self.mip_modes.insert(tid, mode);
+    }
+
+    /// Uploads released textures to `dev`, along with those whose mip mode has changed.
+    /// Textures without a CPU copy are left as they are.
+    pub fn restore(&mut self, dev: &ID3D11Device, ctx: &ID3D11DeviceContext) -> Result<()> {
+        for (tid, tex) in self.allocated.iter_mut() {
+            let pixels = match &tex.pixels {
+                Some(pixels) => pixels,
+                None => continue,
+            };
+
+            let mip = self.mip_modes.get(tid).copied().unwrap_or_default();
+            if tex.gpu.as_ref().map(|gpu| gpu.mip) != Some(mip) {
+                tex.gpu = Some(Self::upload(
+                    dev,
+                    ctx,
+                    pixels,
+                    [tex.width, tex.height],
+                    self.format,
+                    mip,
+                )?);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Views owned by the game, keyed by the number inside [`TextureId::User`].
+/// Kept outside of the app's data, so they can be managed from inside of the ui.
+#[derive(Default)]
+pub struct NativeTextures {
+    /// `None` after the device has changed, until the game gives a new view.
+    views: BTreeMap<u64, Option<ID3D11ShaderResourceView>>,
+    next_id: u64,
+}
+
+impl NativeTextures {
+    pub const fn new() -> Self {
+        Self {
+            views: BTreeMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Makes `view` drawable by egui, ids are never reused.
+    pub fn register(&mut self, view: ID3D11ShaderResourceView) -> TextureId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.views.insert(id, Some(view));
+        TextureId::User(id)
+    }
+
+    pub fn update(&mut self, tid: TextureId, view: ID3D11ShaderResourceView) -> Result<()> {
+        match tid {
+            TextureId::User(id) if self.views.contains_key(&id) => {
+                self.views.insert(id, Some(view));
+                Ok(())
+            }
+            _ => Err(Error::UnknownTexture(tid)),
+        }
+    }
+
+    pub fn free(&mut self, tid: TextureId) -> Result<()> {
+        match tid {
+            TextureId::User(id) if self.views.remove(&id).is_some() => Ok(()),
+            _ => Err(Error::UnknownTexture(tid)),
+        }
+    }
+
+    pub fn get(&self, tid: TextureId) -> Option<ID3D11ShaderResourceView> {
+        match tid {
+            TextureId::User(id) => self.views.get(&id).cloned().flatten(),
+            TextureId::Managed(_) => None,
+        }
+    }
+
+    /// Drops views of the old device, ids stay registered until they are freed.
+    pub fn mark_stale(&mut self) {
+        for view in self.views.values_mut() {
+            *view = None;
+        }
     }
 }
 
 impl TextureAllocator {
-    fn allocate_new(&mut self, dev: &ID3D11Device, tid: TextureId, image: ImageData) {
-        let tex = Self::allocate_texture(dev, image);
+    fn allocate_new(
+        &mut self,
+        dev: &ID3D11Device,
+        ctx: &ID3D11DeviceContext,
+        tid: TextureId,
+        image: ImageData,
+        filter: TextureFilter,
+    ) -> Result<()> {
+        let [width, height] = image.size();
+        // Direct3D can't create such textures.
+        if width == 0 || height == 0 {
+            return Err(Error::EmptyTexture(tid));
+        }
+
+        let pixels = image_pixels(image);
+        let mip = self.mip_modes.get(&tid).copied().unwrap_or_default();
+        let keep = self.keeps_pixels(tid, mip);
+
+        let gpu = Self::upload(dev, ctx, &pixels, [width, height], self.format, mip)?;
+        let tex = ManagedTexture {
+            gpu: Some(gpu),
+            pixels: keep.then_some(pixels),
+            width,
+            height,
+            filter,
+            policy: self.policy,
+        };
+
+        self.lost.remove(&tid);
         self.allocated.insert(tid, tex);
+        Ok(())
+    }
+
+    /// Whether a CPU copy of `tid` is kept under the current policy.
+    fn keeps_pixels(&self, tid: TextureId, mip: MipMode) -> bool {
+        self.policy == TextureMemoryPolicy::KeepCpuCopy || mip == MipMode::Cpu || tid == FONT_ATLAS
     }
 
     fn free(&mut self, tid: TextureId) -> bool {
+        self.mip_modes.remove(&tid);
+        self.lost.remove(&tid);
         self.allocated.remove(&tid).is_some()
     }
 
     fn update_partial(
         &mut self,
-        ctx: &ID3D11DeviceContext,
+        sink: &impl UploadSink,
         tid: TextureId,
         image: ImageData,
-        [nx, ny]: [usize; 2],
-    ) -> bool {
-        if let Some(old) = self.allocated.get_mut(&tid) {
-            let subr = unsafe {
-                expect!(
-                    ctx.Map(&old.texture, 0, D3D11_MAP_WRITE_DISCARD, 0),
-                    "Failed to map subresource"
-                )
-            }
-            .pData;
-
-            match image {
-                ImageData::Font(f) => unsafe {
-                    let data = from_raw_parts_mut(subr as *mut Color32, old.pixels.len());
-                    data.as_mut_ptr()
-                        .copy_from_nonoverlapping(old.pixels.as_ptr(), old.pixels.len());
-
-                    let new: Vec<Color32> = f
-                        .pixels
-                        .iter()
-                        .map(|a| Color32::from_rgba_premultiplied(255, 255, 255, (a * 255.) as u8))
-                        .collect();
-
-                    for y in 0..f.height() {
-                        for x in 0..f.width() {
-                            let whole = (ny + y) * old.width + nx + x;
-                            let frac = y * f.width() + x;
-                            old.pixels[whole] = new[frac];
-                            data[whole] = new[frac];
-                        }
-                    }
-                },
-                _ => unreachable!(),
-            }
+        filter: TextureFilter,
+        [x, y]: [usize; 2],
+    ) -> Result<()> {
+        // Nothing to patch, egui isn't going to send the whole texture again.
+        if self.lost.contains(&tid) {
+            return Ok(());
+        }
 
-            unsafe {
-                ctx.Unmap(&old.texture, 0);
-            }
+        let old = self
+            .allocated
+            .get_mut(&tid)
+            .ok_or(Error::UnknownTexture(tid))?;
 
-            true
-        } else {
-            false
+        old.filter = filter;
+
+        let [width, height] = image.size();
+        let region = Region {
+            x,
+            y,
+            width,
+            height,
+        };
+        let pixels = image_pixels(image);
+        if let Some(copy) = &mut old.pixels {
+            patch_region(copy, old.width, region, &pixels);
         }
+
+        sink.write_region(old, region, &pixels);
+        Ok(())
     }
 
-    fn allocate_texture(dev: &ID3D11Device, image: ImageData) -> ManagedTexture {
-        let desc = D3D11_TEXTURE2D_DESC {
-            Width: image.width() as _,
-            Height: image.height() as _,
+    /// Brings levels after the first one up to date with `pixels`.
+    /// CPU mips are rebuilt from the whole image, even if only a part of it has changed.
+    fn update_mips(
+        ctx: &ID3D11DeviceContext,
+        gpu: &GpuTexture,
+        pixels: Option<&[Color32]>,
+        [width, height]: [usize; 2],
+    ) {
+        unsafe {
+            match (gpu.mip, pixels) {
+                (MipMode::Gpu, _) => ctx.GenerateMips(&gpu.resource),
+                // Textures with CPU mips always keep their pixels.
+                (MipMode::Cpu, Some(pixels)) => {
+                    let levels = mip_chain(pixels, width, height);
+                    for (i, level) in levels.iter().enumerate() {
+                        ctx.UpdateSubresource(
+                            &gpu.texture,
+                            i as u32 + 1,
+                            null(),
+                            level.pixels.as_ptr() as _,
+                            (level.width * size_of::<Color32>()) as _,
+                            0,
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn upload(
+        dev: &ID3D11Device,
+        ctx: &ID3D11DeviceContext,
+        pixels: &[Color32],
+        [width, height]: [usize; 2],
+        format: DXGI_FORMAT,
+        mip: MipMode,
+    ) -> Result<GpuTexture> {
+        let mut desc = D3D11_TEXTURE2D_DESC {
+            Width: width as _,
+            Height: height as _,
             MipLevels: 1,
             ArraySize: 1,
-            Format: DXGI_FORMAT_R8G8B8A8_UNORM,
+            Format: format,
             SampleDesc: DXGI_SAMPLE_DESC {
                 Count: 1,
                 Quality: 0,
             },
-            Usage: D3D11_USAGE_DYNAMIC,
+            Usage: D3D11_USAGE_DEFAULT,
             BindFlags: D3D11_BIND_SHADER_RESOURCE,
-            CPUAccessFlags: D3D11_CPU_ACCESS_WRITE,
             ..Default::default()
         };
 
-        // rust is cringe sometimes
-        let width = image.width();
-        let pixels = match image {
-            ImageData::Color(c) => c.pixels,
-            ImageData::Font(f) => f
-                .pixels
-                .iter()
-                .map(|a| Color32::from_rgba_premultiplied(255, 255, 255, (a * 255.) as u8))
-                .collect(),
+        if mip != MipMode::Off {
+            desc.MipLevels = level_count(width, height);
+        }
+
+        // GenerateMips renders into the texture, so it has to be a render target.
+        if mip == MipMode::Gpu {
+            desc.BindFlags = D3D11_BIND_SHADER_RESOURCE | D3D11_BIND_RENDER_TARGET;
+            desc.MiscFlags = D3D11_RESOURCE_MISC_GENERATE_MIPS;
+        }
+
+        let levels = match mip {
+            MipMode::Cpu => mip_chain(pixels, width, height),
+            _ => vec![],
         };
 
-        let data = D3D11_SUBRESOURCE_DATA {
+        let mut data = vec![D3D11_SUBRESOURCE_DATA {
             pSysMem: pixels.as_ptr() as _,
             SysMemPitch: (width * size_of::<Color32>()) as u32,
             SysMemSlicePitch: 0,
-        };
+        }];
+        data.extend(levels.iter().map(|level| D3D11_SUBRESOURCE_DATA {
+            pSysMem: level.pixels.as_ptr() as _,
+            SysMemPitch: (level.width * size_of::<Color32>()) as u32,
+            SysMemSlicePitch: 0,
+        }));
 
         unsafe {
-            let texture = expect!(
-                dev.CreateTexture2D(&desc, &data),
-                "Failed to create a texture"
-            );
+            // Initial data has to cover every level, generated ones are filled after creation.
+            let texture = dev
+                .CreateTexture2D(
+                    &desc,
+                    if mip == MipMode::Gpu {
+                        null()
+                    } else {
+                        data.as_ptr()
+                    },
+                )
+                .map_err(|e| Error::Texture("Failed to create a texture", e))?;
 
             let desc = D3D11_SHADER_RESOURCE_VIEW_DESC {
-                Format: DXGI_FORMAT_R8G8B8A8_UNORM,
+                Format: format,
                 ViewDimension: D3D11_SRV_DIMENSION_TEXTURE2D,
                 Anonymous: D3D11_SHADER_RESOURCE_VIEW_DESC_0 {
                     Texture2D: D3D11_TEX2D_SRV {
@@ -159,17 +495,366 @@ impl TextureAllocator {
                 },
             };
 
-            let resource = expect!(
-                dev.CreateShaderResourceView(&texture, &desc),
-                "Failed to create shader resource view"
+            let resource = dev
+                .CreateShaderResourceView(&texture, &desc)
+                .map_err(|e| Error::Texture("Failed to create shader resource view", e))?;
+
+            if mip == MipMode::Gpu {
+                ctx.UpdateSubresource(&texture, 0, null(), data[0].pSysMem, data[0].SysMemPitch, 0);
+                ctx.GenerateMips(&resource);
+            }
+
+            Ok(GpuTexture {
+                resource,
+                texture,
+                mip,
+            })
+        }
+    }
+}
+
+/// Converts image of either kind into `sRGBA` pixels, row by row.
+fn image_pixels(image: ImageData) -> Vec<Color32> {
+    match image {
+        ImageData::Color(c) => c.pixels,
+        ImageData::Font(f) => f.srgba_pixels(1.).collect(),
+    }
+}
+
+/// Part of a texture replaced by a partial update, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Region {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+/// Receives partial updates once the CPU copy is patched.
+/// Implemented by the device context, kept apart so updates can be followed without a device.
+trait UploadSink {
+    /// Writes `pixels` of `region` into the device copy of `tex`.
+    fn write_region(&self, tex: &ManagedTexture, region: Region, pixels: &[Color32]);
+}
+
+impl UploadSink for ID3D11DeviceContext {
+    fn write_region(&self, tex: &ManagedTexture, region: Region, pixels: &[Color32]) {
+        // Released texture gets the new pixels once it's restored.
+        let gpu = match &tex.gpu {
+            Some(gpu) => gpu,
+            None => return,
+        };
+
+        let dst = D3D11_BOX {
+            left: region.x as _,
+            top: region.y as _,
+            front: 0,
+            right: (region.x + region.width) as _,
+            bottom: (region.y + region.height) as _,
+            back: 1,
+        };
+
+        unsafe {
+            self.UpdateSubresource(
+                &gpu.texture,
+                0,
+                &dst,
+                pixels.as_ptr() as _,
+                (region.width * size_of::<Color32>()) as _,
+                0,
             );
+        }
 
+        TextureAllocator::update_mips(self, gpu, tex.pixels.as_deref(), [tex.width, tex.height]);
+    }
+}
+
+/// Copies `region` of `src` into `pixels`, which are `width` pixels wide.
+fn patch_region(pixels: &mut [Color32], width: usize, region: Region, src: &[Color32]) {
+    for (row, src) in src.chunks_exact(region.width).enumerate() {
+        let start = (region.y + row) * width + region.x;
+        pixels[start..start + region.width].copy_from_slice(src);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{ColorImage, FontImage};
+    use std::cell::RefCell;
+
+    const RED: Color32 = Color32::from_rgba_premultiplied(255, 0, 0, 255);
+
+    /// Remembers every region instead of uploading it.
+    #[derive(Default)]
+    struct RecordingSink {
+        writes: RefCell<Vec<(Region, Vec<Color32>)>>,
+    }
+
+    impl UploadSink for RecordingSink {
+        fn write_region(&self, _: &ManagedTexture, region: Region, pixels: &[Color32]) {
+            self.writes.borrow_mut().push((region, pixels.to_vec()));
+        }
+    }
+
+    /// Allocator holding a single transparent `width` x `height` texture without a device copy.
+    fn allocator(tid: TextureId, [width, height]: [usize; 2]) -> TextureAllocator {
+        let mut alloc = TextureAllocator::new(ColorMode::Linear);
+        alloc.allocated.insert(
+            tid,
             ManagedTexture {
+                gpu: None,
+                pixels: Some(vec![Color32::TRANSPARENT; width * height]),
                 width,
-                resource,
-                pixels,
-                texture,
+                height,
+                filter: TextureFilter::Linear,
+                policy: TextureMemoryPolicy::KeepCpuCopy,
+            },
+        );
+        alloc
+    }
+
+    fn pixels(alloc: &TextureAllocator, tid: TextureId) -> &[Color32] {
+        alloc.allocated[&tid].pixels.as_deref().unwrap()
+    }
+
+    #[test]
+    fn color_region_is_patched_and_uploaded() {
+        let tid = TextureId::Managed(1);
+        let mut alloc = allocator(tid, [4, 3]);
+        let sink = RecordingSink::default();
+
+        let image = ColorImage::new([2, 2], RED);
+        alloc
+            .update_partial(&sink, tid, image.into(), TextureFilter::Nearest, [1, 1])
+            .unwrap();
+
+        let region = Region {
+            x: 1,
+            y: 1,
+            width: 2,
+            height: 2,
+        };
+        assert_eq!(*sink.writes.borrow(), [(region, vec![RED; 4])]);
+
+        let t = Color32::TRANSPARENT;
+        #[rustfmt::skip]
+        assert_eq!(pixels(&alloc, tid), [
+            t, t, t, t,
+            t, RED, RED, t,
+            t, RED, RED, t,
+        ]);
+        assert_eq!(alloc.get_filter(tid), Some(TextureFilter::Nearest));
+    }
+
+    #[test]
+    fn font_region_is_converted_to_white() {
+        let tid = TextureId::Managed(0);
+        let mut alloc = allocator(tid, [3, 2]);
+        let sink = RecordingSink::default();
+
+        let mut image = FontImage::new([2, 1]);
+        image.pixels = vec![1., 0.];
+        alloc
+            .update_partial(&sink, tid, image.into(), TextureFilter::Linear, [1, 0])
+            .unwrap();
+
+        let (w, t) = (Color32::WHITE, Color32::TRANSPARENT);
+        let writes = sink.writes.borrow();
+        assert_eq!(writes.len(), 1);
+        assert_eq!(writes[0].1, [w, t]);
+        assert_eq!(pixels(&alloc, tid), [t, w, t, t, t, t]);
+    }
+
+    #[test]
+    fn edge_aligned_regions() {
+        let tid = TextureId::Managed(1);
+        let mut alloc = allocator(tid, [3, 3]);
+        let sink = RecordingSink::default();
+        let t = Color32::TRANSPARENT;
+
+        // Last column.
+        let column = ColorImage::new([1, 3], RED);
+        alloc
+            .update_partial(&sink, tid, column.into(), TextureFilter::Linear, [2, 0])
+            .unwrap();
+        assert_eq!(pixels(&alloc, tid), [t, t, RED, t, t, RED, t, t, RED]);
+
+        // Last row.
+        let row = ColorImage::new([3, 1], Color32::WHITE);
+        alloc
+            .update_partial(&sink, tid, row.into(), TextureFilter::Linear, [0, 2])
+            .unwrap();
+        let w = Color32::WHITE;
+        assert_eq!(pixels(&alloc, tid), [t, t, RED, t, t, RED, w, w, w]);
+
+        // Whole texture.
+        let all = ColorImage::new([3, 3], Color32::BLACK);
+        alloc
+            .update_partial(&sink, tid, all.into(), TextureFilter::Linear, [0, 0])
+            .unwrap();
+        assert_eq!(pixels(&alloc, tid), [Color32::BLACK; 9]);
+
+        let regions: Vec<_> = sink.writes.borrow().iter().map(|(r, _)| *r).collect();
+        assert_eq!(
+            regions,
+            [
+                Region {
+                    x: 2,
+                    y: 0,
+                    width: 1,
+                    height: 3
+                },
+                Region {
+                    x: 0,
+                    y: 2,
+                    width: 3,
+                    height: 1
+                },
+                Region {
+                    x: 0,
+                    y: 0,
+                    width: 3,
+                    height: 3
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_texture_is_reported() {
+        let mut alloc = allocator(TextureId::Managed(1), [2, 2]);
+        let sink = RecordingSink::default();
+
+        let image = ColorImage::new([1, 1], RED);
+        let result = alloc.update_partial(
+            &sink,
+            TextureId::Managed(2),
+            image.into(),
+            TextureFilter::Linear,
+            [0, 0],
+        );
+
+        assert!(matches!(
+            result,
+            Err(Error::UnknownTexture(TextureId::Managed(2)))
+        ));
+        assert!(sink.writes.borrow().is_empty());
+        assert_eq!(
+            pixels(&alloc, TextureId::Managed(1)),
+            [Color32::TRANSPARENT; 4]
+        );
+    }
+
+    #[test]
+    fn texture_without_cpu_copy_is_still_uploaded() {
+        let tid = TextureId::Managed(1);
+        let mut alloc = allocator(tid, [2, 2]);
+        alloc.allocated.get_mut(&tid).unwrap().pixels = None;
+        let sink = RecordingSink::default();
+
+        let image = ColorImage::new([1, 1], RED);
+        alloc
+            .update_partial(&sink, tid, image.into(), TextureFilter::Linear, [1, 1])
+            .unwrap();
+
+        assert_eq!(sink.writes.borrow().len(), 1);
+        assert!(alloc.allocated[&tid].pixels.is_none());
+    }
+
+    #[test]
+    fn font_atlas_keeps_pixels_without_cpu_copies() {
+        let mut alloc = TextureAllocator::new(ColorMode::Linear);
+        alloc.set_memory_policy(TextureMemoryPolicy::GpuOnly);
+
+        assert!(alloc.keeps_pixels(FONT_ATLAS, MipMode::Off));
+        assert!(alloc.keeps_pixels(TextureId::Managed(1), MipMode::Cpu));
+        assert!(!alloc.keeps_pixels(TextureId::Managed(1), MipMode::Off));
+        assert!(!alloc.keeps_pixels(TextureId::Managed(1), MipMode::Gpu));
+
+        alloc.set_memory_policy(TextureMemoryPolicy::KeepCpuCopy);
+        assert!(alloc.keeps_pixels(TextureId::Managed(1), MipMode::Off));
+    }
+
+    #[test]
+    fn updates_of_released_gpu_only_textures_are_dropped() {
+        let (kept, gone) = (TextureId::Managed(0), TextureId::Managed(1));
+        let mut alloc = allocator(kept, [2, 2]);
+        alloc.allocated.insert(
+            gone,
+            ManagedTexture {
+                gpu: None,
+                pixels: None,
+                width: 2,
+                height: 2,
+                filter: TextureFilter::Linear,
+                policy: TextureMemoryPolicy::KeepCpuCopy,
+            },
+        );
+        let sink = RecordingSink::default();
+
+        alloc.release();
+        assert!(alloc.allocated.contains_key(&kept));
+        assert!(!alloc.allocated.contains_key(&gone));
+
+        let image = ColorImage::new([1, 1], RED);
+        alloc
+            .update_partial(
+                &sink,
+                gone,
+                image.clone().into(),
+                TextureFilter::Linear,
+                [0, 0],
+            )
+            .unwrap();
+        assert!(sink.writes.borrow().is_empty());
+
+        alloc
+            .update_partial(
+                &sink,
+                kept,
+                image.clone().into(),
+                TextureFilter::Linear,
+                [0, 0],
+            )
+            .unwrap();
+        assert_eq!(sink.writes.borrow().len(), 1);
+
+        // Once egui frees the texture its id is unknown again.
+        alloc.free(gone);
+        let result = alloc.update_partial(&sink, gone, image.into(), TextureFilter::Linear, [0, 0]);
+        assert!(matches!(result, Err(Error::UnknownTexture(tid)) if tid == gone));
+    }
+
+    #[test]
+    fn memory_stats_are_split_by_policy() {
+        let (kept, gpu_only) = (TextureId::Managed(1), TextureId::Managed(2));
+        let mut alloc = allocator(kept, [2, 2]);
+        alloc.allocated.insert(
+            gpu_only,
+            ManagedTexture {
+                gpu: None,
+                pixels: Some(vec![Color32::TRANSPARENT; 3]),
+                width: 3,
+                height: 1,
+                filter: TextureFilter::Linear,
+                policy: TextureMemoryPolicy::GpuOnly,
+            },
+        );
+
+        let stats = alloc.memory_stats();
+        assert_eq!(stats.keep_cpu_copy.cpu_textures, 1);
+        assert_eq!(stats.keep_cpu_copy.cpu_bytes, 16);
+        assert_eq!(stats.policy(TextureMemoryPolicy::GpuOnly).cpu_textures, 1);
+        assert_eq!(stats.policy(TextureMemoryPolicy::GpuOnly).cpu_bytes, 12);
+        assert_eq!(
+            stats.total(),
+            TextureMemoryUsage {
+                cpu_textures: 2,
+                cpu_bytes: 28,
+                gpu_textures: 0,
+                gpu_bytes: 0,
             }
-        }
+        );
     }
 }
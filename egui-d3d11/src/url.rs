@@ -0,0 +1,183 @@
+//! Opening of links clicked inside the ui, e.g. with `ui.hyperlink`.
+use egui::output::OpenUrl;
+use std::{cell::RefCell, iter::once, rc::Rc};
+use windows::{
+    core::PCWSTR,
+    Win32::{Foundation::HWND, UI::Shell::ShellExecuteW},
+};
+
+const SW_SHOWNORMAL: i32 = 1;
+
+type NewTabHandler = Box<dyn FnMut(&str)>;
+
+/// Receives every link egui asks to open and passed the filter set with
+/// [`crate::DirectX11App::set_url_filter`].
+pub trait UrlOpener {
+    fn open(&mut self, url: &OpenUrl);
+}
+
+/// Opens links with `ShellExecuteW`, i.e. in the user's default browser.
+/// Shell has no notion of tabs, links with [`OpenUrl::new_tab`] go to the handler
+/// set with [`ShellUrlOpener::on_new_tab`] if there is one, and to the shell otherwise.
+#[derive(Default)]
+pub struct ShellUrlOpener {
+    new_tab: Option<NewTabHandler>,
+}
+
+impl ShellUrlOpener {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets closure which opens links egui wants in a new tab, e.g. through the game's own browser.
+    pub fn on_new_tab(mut self, handler: impl FnMut(&str) + 'static) -> Self {
+        self.new_tab = Some(Box::new(handler));
+        self
+    }
+}
+
+impl UrlOpener for ShellUrlOpener {
+    fn open(&mut self, url: &OpenUrl) {
+        if let (true, Some(new_tab)) = (url.new_tab, &mut self.new_tab) {
+            return new_tab(&url.url);
+        }
+
+        let file = to_wide(&url.url);
+        let operation = to_wide("open");
+
+        unsafe {
+            ShellExecuteW(
+                HWND::default(),
+                PCWSTR(operation.as_ptr()),
+                PCWSTR(file.as_ptr()),
+                PCWSTR::default(),
+                PCWSTR::default(),
+                SW_SHOWNORMAL,
+            );
+        }
+    }
+}
+
+/// Ignores all links.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopUrlOpener;
+
+impl UrlOpener for NoopUrlOpener {
+    fn open(&mut self, _: &OpenUrl) {}
+}
+
+/// Remembers links instead of opening them. All clones share the same list,
+/// so keep one around to inspect what the ui tried to open.
+#[derive(Debug, Default, Clone)]
+pub struct RecordingUrlOpener {
+    opened: Rc<RefCell<Vec<OpenUrl>>>,
+}
+
+impl RecordingUrlOpener {
+    /// Links received so far, oldest first.
+    pub fn opened(&self) -> Vec<OpenUrl> {
+        self.opened.borrow().clone()
+    }
+
+    /// Forgets all received links.
+    pub fn clear(&self) {
+        self.opened.borrow_mut().clear();
+    }
+}
+
+impl UrlOpener for RecordingUrlOpener {
+    fn open(&mut self, url: &OpenUrl) {
+        self.opened.borrow_mut().push(url.clone());
+    }
+}
+
+/// Checks if `url` uses `http` or `https` scheme.
+/// Used as the default filter, anything else could launch an arbitrary program through the shell.
+pub fn is_web_url(url: &str) -> bool {
+    let scheme = match url.split_once(':') {
+        Some((scheme, _)) => scheme,
+        None => return false,
+    };
+
+    scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https")
+}
+
+/// Passes `url` to `opener` if `filter` allows it.
+pub(crate) fn open_url(
+    url: &OpenUrl,
+    filter: &mut dyn FnMut(&OpenUrl) -> bool,
+    opener: &mut dyn UrlOpener,
+) {
+    if filter(url) {
+        opener.open(url);
+    }
+}
+
+fn to_wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(once(0)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn web_urls_are_recognized() {
+        assert!(is_web_url("http://example.com"));
+        assert!(is_web_url("https://example.com/path?q=1"));
+        assert!(is_web_url("HTTPS://example.com"));
+        assert!(is_web_url("Http://example.com"));
+    }
+
+    #[test]
+    fn other_schemes_are_rejected() {
+        assert!(!is_web_url("javascript:alert(1)"));
+        assert!(!is_web_url("file:///C:/Windows/System32/calc.exe"));
+        assert!(!is_web_url("C:\\Windows\\System32\\calc.exe"));
+        assert!(!is_web_url("ms-settings:"));
+        assert!(!is_web_url("https"));
+        assert!(!is_web_url("example.com"));
+        assert!(!is_web_url(""));
+    }
+
+    #[test]
+    fn filtered_links_are_not_opened() {
+        let recording = RecordingUrlOpener::default();
+        let mut opener = recording.clone();
+        let mut filter = |url: &OpenUrl| is_web_url(&url.url);
+
+        open_url(
+            &OpenUrl::same_tab("https://example.com"),
+            &mut filter,
+            &mut opener,
+        );
+        open_url(&OpenUrl::new_tab("file:///C:/"), &mut filter, &mut opener);
+        open_url(
+            &OpenUrl::new_tab("http://example.com"),
+            &mut filter,
+            &mut opener,
+        );
+
+        let opened = recording.opened();
+        assert_eq!(opened.len(), 2);
+        assert_eq!(opened[0].url, "https://example.com");
+        assert!(!opened[0].new_tab);
+        assert_eq!(opened[1].url, "http://example.com");
+        assert!(opened[1].new_tab);
+
+        recording.clear();
+        assert!(recording.opened().is_empty());
+    }
+
+    #[test]
+    fn new_tab_links_go_to_handler() {
+        let tabs = Rc::new(RefCell::new(vec![]));
+        let mut opener = ShellUrlOpener::new().on_new_tab({
+            let tabs = tabs.clone();
+            move |url| tabs.borrow_mut().push(url.to_owned())
+        });
+
+        opener.open(&OpenUrl::new_tab("https://example.com"));
+        assert_eq!(*tabs.borrow(), ["https://example.com"]);
+    }
+}
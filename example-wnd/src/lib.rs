@@ -92,7 +92,9 @@ unsafe extern "stdcall" fn hk_wnd_proc(
     wparam: WPARAM,
     lparam: LPARAM,
 ) -> LRESULT {
-    APP.wnd_proc(msg, wparam, lparam);
+    if APP.wnd_proc_filtered(msg, wparam, lparam).is_consumed() {
+        return LRESULT(0);
+    }
 
     CallWindowProcW(OLD_WND_PROC.unwrap(), hwnd, msg, wparam, lparam)
 }